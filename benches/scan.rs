@@ -0,0 +1,81 @@
+// Sequential WalkDir scan vs the parallel walker on a generated fixture tree.
+// Run with `cargo bench`, or `cargo bench -- /some/real/path` to time an existing folder.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use DiskScanner::scanner::{scan_parallel, DirTree};
+
+const FANOUT: usize = 12;  // directories per level
+const LEVELS: usize = 3;
+const FILES_PER_DIR: usize = 20;
+const RUNS: u32 = 5;
+
+fn main() {
+    let custom_root = std::env::args().skip(1).find(|arg| !arg.starts_with('-'));
+    let (root, generated) = match custom_root {
+        Some(path) => (PathBuf::from(path), false),
+        None => (generate_fixture(), true),
+    };
+
+    let sequential = DirTree::scan(&root).expect("fixture root is readable");
+    println!(
+        "fixture: {} ({} entries, {} bytes)",
+        root.display(),
+        sequential.len(),
+        sequential.node(sequential.root()).total_size
+    );
+
+    report("sequential", time(|| DirTree::scan(&root)));
+
+    let cpus = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let mut threads = 1;
+    while threads <= cpus.max(1) {
+        let tree = scan_parallel(&root, threads).expect("fixture root is readable");
+        assert_eq!(tree.len(), sequential.len(), "parallel walk saw a different tree");
+        assert_eq!(
+            tree.node(tree.root()).total_size,
+            sequential.node(sequential.root()).total_size
+        );
+
+        report(&format!("parallel x{}", threads), time(|| scan_parallel(&root, threads)));
+        threads *= 2;
+    }
+
+    if generated {
+        let _ = fs::remove_dir_all(&root);
+    }
+}
+
+fn time<T>(mut scan: impl FnMut() -> T) -> Duration {
+    let mut best = Duration::MAX;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        std::hint::black_box(scan());
+        best = best.min(start.elapsed());
+    }
+    best
+}
+
+fn report(name: &str, best: Duration) {
+    println!("{:<14} best of {}: {:>10.2?}", name, RUNS, best);
+}
+
+fn generate_fixture() -> PathBuf {
+    let root = std::env::temp_dir().join(format!("diskscanner-bench-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fill(&root, LEVELS);
+    root
+}
+
+fn fill(dir: &Path, levels_left: usize) {
+    fs::create_dir_all(dir).expect("can create fixture directory");
+    for i in 0..FILES_PER_DIR {
+        fs::write(dir.join(format!("file{}.bin", i)), vec![0u8; i * 97]).expect("can write fixture file");
+    }
+    if levels_left > 0 {
+        for i in 0..FANOUT {
+            fill(&dir.join(format!("dir{}", i)), levels_left - 1);
+        }
+    }
+}
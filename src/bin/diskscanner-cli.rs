@@ -0,0 +1,526 @@
+// Headless front end to the scanner, for machines without a display.
+//
+//   diskscanner-cli scan  <path> [--max-depth N]   sorted, indented tree like `du -h --max-depth`
+//   diskscanner-cli top   <path> [-n N]            largest files and folders
+//   diskscanner-cli stats <path>                   totals of one folder
+//   diskscanner-cli usage [path]                   drive usage of path, or of every mount
+//   diskscanner-cli ncdu  <path>                   dump the tree in ncdu's export format
+//   diskscanner-cli snapshot <path> -o FILE        save a snapshot the GUI can open
+//   diskscanner-cli diff  <old> <new>              what grew and shrank between two snapshots
+//   diskscanner-cli history <path> [--record]      size of a folder over every recorded scan
+//   diskscanner-cli duplicates <path> [-n N]       groups of identical files, most reclaimable first
+//                   [--link hardlink|reflink]      and replace all but the first copy with links to it
+//   diskscanner-cli empty <path>                   empty folders and zero-byte files
+//   diskscanner-cli symlinks <path>                symlinks and their targets, broken ones first
+//
+// scan, top, stats and usage take --format json|csv|ndjson to print machine readable records instead.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::SystemTime;
+use DiskScanner::scanner::utils::{human_readable_delta, human_readable_size, human_readable_time};
+use DiskScanner::scanner::{
+    drive_usage, list_mounts, scan_tree, write_drive_usage, write_entries, write_folder_stats, write_ncdu, save_snapshot, load_snapshot, diff_snapshots, DirTree, DriveUsage,
+    ExportFormat, FolderStats, History, find_duplicates, find_empty, find_symlinks, largest_entries, link_duplicates, LinkMode, NodeId, PathDelta, ScanControl, ScanOptions, Snapshot, SortOrder,
+};
+
+const USAGE: &str = "usage: diskscanner-cli <command> [options] [path]
+
+commands:
+  scan <path>     print the folder tree, largest first
+  top <path>      print the largest files and folders
+  stats <path>    print the totals of a folder
+  usage [path]    print drive usage of path, or of every mounted filesystem
+  ncdu <path>     write the tree in ncdu's JSON export format (open it with ncdu -f)
+  snapshot <path> save a binary snapshot to -o FILE, open it from the GUI home page
+  diff <old> <new>
+                  compare two snapshots (or a snapshot and a folder, scanned now)
+  history <path>  print the size of a folder at every scan in the history file
+  duplicates <path>
+                  print groups of files with identical content
+  empty <path>    print empty folders (holding only empty folders) and zero-byte files
+  symlinks <path> print symlinks with their targets, dangling and looping ones first
+
+options:
+  --max-depth N         only print folders down to depth N (scan, default 1)
+  -n N                  how many entries to print (top, diff and duplicates, default 20)
+  --min-size BYTES      ignore smaller files when looking for duplicates (default 1)
+  --link MODE           hardlink or reflink: replace every duplicate but the first of its group with a link to it
+  --disk-usage          sizes as allocated on disk instead of apparent size
+  --sort ORDER          size (default), name, modified or walk
+  --exclude PATTERN     skip paths matching a .gitignore style pattern, repeatable
+  --include PATTERN     only count files matching the pattern, repeatable
+  --gitignore           honor .gitignore and .ignore files
+  --no-hidden           skip dot files and dot directories
+  --follow-symlinks     descend into symlinked directories
+  -x, --one-file-system don't cross into other mounts
+  --threads N           0 uses every CPU (default), 1 the sequential walker
+  --format FORMAT       json, csv or ndjson instead of text (scan, top, stats, usage); scan writes every entry
+  --record              scan first and add the result to the history (history)
+  -o, --output FILE     write to FILE instead of stdout
+
+The history file is $DISKSCANNER_HISTORY, or diskscanner/history.ndjson in the user data directory.";
+
+// Commands that can print --format records
+const FORMAT_COMMANDS: [&str; 4] = ["scan", "top", "stats", "usage"];
+
+struct Cli {
+    command: String,
+    path: Option<PathBuf>,
+    other_path: Option<PathBuf>, // second path of `diff`
+    options: ScanOptions,
+    max_depth: usize,
+    limit: usize,
+    show_allocated: bool,
+    format: Option<ExportFormat>,
+    output: Option<PathBuf>,
+    record: bool, // `history --record`
+    min_size: u64, // `duplicates --min-size`
+    link: Option<LinkMode>, // `duplicates --link`
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let cli = match parse_args(&args) {
+        Ok(cli) => cli,
+        Err(message) => {
+            eprintln!("diskscanner-cli: {}\n\n{}", message, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let result = match cli.command.as_str() {
+        "scan" => run_scan(&cli),
+        "top" => run_top(&cli),
+        "stats" => run_stats(&cli),
+        "usage" => run_usage(&cli),
+        "ncdu" => run_ncdu(&cli),
+        "snapshot" => run_snapshot(&cli),
+        "diff" => run_diff(&cli),
+        "history" => run_history(&cli),
+        "duplicates" => run_duplicates(&cli),
+        "empty" => run_empty(&cli),
+        "symlinks" => run_symlinks(&cli),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        other => Err(format!("unknown command '{}'", other)),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("diskscanner-cli: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Cli, String> {
+    let mut args = args.iter();
+    let command = args.next().ok_or("missing command")?.clone();
+
+    let mut cli = Cli {
+        command,
+        path: None,
+        other_path: None,
+        options: ScanOptions::new().sort_order(SortOrder::Size),
+        max_depth: 1,
+        limit: 20,
+        show_allocated: false,
+        format: None,
+        output: None,
+        record: false,
+        min_size: 1,
+        link: None,
+    };
+    let mut exclude = Vec::new();
+    let mut include = Vec::new();
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().cloned().ok_or(format!("{} needs a value", name));
+        match arg.as_str() {
+            "--max-depth" | "-d" => cli.max_depth = parse_number(arg, &value(arg)?)?,
+            "-n" | "--limit" => cli.limit = parse_number(arg, &value(arg)?)?,
+            "--min-size" => cli.min_size = parse_number(arg, &value(arg)?)? as u64,
+            "--link" => cli.link = Some(parse_link_mode(&value(arg)?)?),
+            "--threads" => cli.options.threads = parse_number(arg, &value(arg)?)?,
+            "--sort" => cli.options.sort_order = parse_sort(&value(arg)?)?,
+            "--format" => {
+                let name = value(arg)?;
+                cli.format = Some(ExportFormat::from_name(&name).ok_or(format!("unknown format '{}'", name))?);
+            }
+            "--output" | "-o" => cli.output = Some(PathBuf::from(value(arg)?)),
+            "--exclude" => exclude.push(value(arg)?),
+            "--include" => include.push(value(arg)?),
+            "--disk-usage" => cli.show_allocated = true,
+            "--record" => cli.record = true,
+            "--gitignore" => cli.options.use_ignore_files = true,
+            "--no-hidden" => cli.options.include_hidden = false,
+            "--follow-symlinks" | "-L" => cli.options.follow_symlinks = true,
+            "--one-file-system" | "-x" => cli.options.one_file_system = true,
+            flag if flag.starts_with('-') && flag.len() > 1 => return Err(format!("unknown option '{}'", flag)),
+            path => {
+                if cli.path.is_none() {
+                    cli.path = Some(PathBuf::from(path));
+                } else if cli.command == "diff" && cli.other_path.is_none() {
+                    cli.other_path = Some(PathBuf::from(path));
+                } else {
+                    return Err(format!("unexpected argument '{}'", path));
+                }
+            }
+        }
+    }
+
+    // Better an error than text output where records were asked for
+    if cli.format.is_some() && !FORMAT_COMMANDS.contains(&cli.command.as_str()) {
+        return Err(format!("'{}' has no --format, only {} do", cli.command, FORMAT_COMMANDS.join(", ")));
+    }
+
+    cli.options = cli.options.exclude(exclude).include(include);
+    Ok(cli)
+}
+
+fn parse_number(name: &str, value: &str) -> Result<usize, String> {
+    value.parse().map_err(|_| format!("{} expects a number, got '{}'", name, value))
+}
+
+fn parse_link_mode(value: &str) -> Result<LinkMode, String> {
+    match value {
+        "hardlink" => Ok(LinkMode::Hardlink),
+        "reflink" => Ok(LinkMode::Reflink),
+        other => Err(format!("unknown link mode '{}'", other)),
+    }
+}
+
+fn parse_sort(value: &str) -> Result<SortOrder, String> {
+    match value {
+        "size" => Ok(SortOrder::Size),
+        "name" => Ok(SortOrder::Name),
+        "modified" => Ok(SortOrder::Modified),
+        "walk" => Ok(SortOrder::WalkOrder),
+        other => Err(format!("unknown sort order '{}'", other)),
+    }
+}
+
+impl Cli {
+    fn required_path(&self) -> Result<&Path, String> {
+        self.path.as_deref().ok_or_else(|| format!("'{}' needs a path", self.command))
+    }
+
+    fn scan(&self) -> Result<DirTree, String> {
+        let path = self.required_path()?;
+        let tree = scan_tree(path, &self.options, &ScanControl::new())
+            .ok_or_else(|| format!("cannot read '{}'", path.display()))?;
+
+        let report = tree.report();
+        if !report.is_empty() {
+            eprintln!("diskscanner-cli: {} paths could not be read, totals may be short", report.len());
+        }
+        Ok(tree)
+    }
+
+    fn writer(&self) -> Result<Box<dyn Write>, String> {
+        match &self.output {
+            Some(path) => {
+                let file = File::create(path).map_err(|e| format!("cannot create '{}': {}", path.display(), e))?;
+                Ok(Box::new(BufWriter::new(file)))
+            }
+            None => Ok(Box::new(io::stdout().lock())),
+        }
+    }
+
+    // A snapshot file as saved, or a folder scanned right now
+    fn snapshot_or_scan(&self, path: &Path) -> Result<Snapshot, String> {
+        if path.is_dir() {
+            let tree = scan_tree(path, &self.options, &ScanControl::new())
+                .ok_or_else(|| format!("cannot read '{}'", path.display()))?;
+            return Ok(Snapshot::new(tree, self.options.clone(), SystemTime::now()));
+        }
+        load_snapshot(path).map_err(|e| format!("cannot open snapshot '{}': {}", path.display(), e))
+    }
+
+    fn size(&self, tree: &DirTree, id: NodeId) -> u64 {
+        let node = tree.node(id);
+        if self.show_allocated {
+            node.allocated_size
+        } else {
+            node.total_size
+        }
+    }
+}
+
+fn run_scan(cli: &Cli) -> Result<(), String> {
+    let tree = cli.scan()?;
+    let mut out = cli.writer()?;
+    match cli.format {
+        Some(format) => {
+            let entries = tree.descendants(tree.root()).into_iter().skip(1).map(|id| &tree.node(id).entry);
+            write_entries(out, entries, format).map_err(write_error)
+        }
+        None => print_tree(cli, &mut out, &tree, tree.root(), 0).map_err(write_error),
+    }
+}
+
+// Folders only, like du; children are already in the requested sort order
+fn print_tree(cli: &Cli, out: &mut dyn Write, tree: &DirTree, id: NodeId, depth: usize) -> io::Result<()> {
+    let node = tree.node(id);
+    let name = if depth == 0 { node.entry.path.display().to_string() } else { node.entry.name.clone() };
+    writeln!(out, "{:>10}  {}{}", human_readable_size(cli.size(tree, id)), "  ".repeat(depth), name)?;
+
+    if depth >= cli.max_depth {
+        return Ok(());
+    }
+    for &child in tree.children(id) {
+        if tree.node(child).entry.is_folder {
+            print_tree(cli, out, tree, child, depth + 1)?;
+        }
+    }
+    Ok(())
+}
+
+fn run_top(cli: &Cli) -> Result<(), String> {
+    let tree = cli.scan()?;
+    let largest = largest_entries(&tree, tree.root(), cli.limit, cli.show_allocated);
+
+    let mut out = cli.writer()?;
+    if let Some(format) = cli.format {
+        let entries = largest.files.iter().chain(&largest.folders).map(|&id| &tree.node(id).entry);
+        return write_entries(out, entries, format).map_err(write_error);
+    }
+    for (title, ids) in [("Largest files", &largest.files), ("Largest folders", &largest.folders)] {
+        writeln!(out, "{}:", title).map_err(write_error)?;
+        for &id in ids {
+            writeln!(out, "{:>10}  {}", human_readable_size(cli.size(&tree, id)), tree.path(id).display())
+                .map_err(write_error)?;
+        }
+        writeln!(out).map_err(write_error)?;
+    }
+    Ok(())
+}
+
+fn run_stats(cli: &Cli) -> Result<(), String> {
+    let tree = cli.scan()?;
+    let stats = tree.folder_stats(tree.root());
+    let mut out = cli.writer()?;
+    if let Some(format) = cli.format {
+        return write_folder_stats(out, &stats, format).map_err(write_error);
+    }
+    for line in folder_stats_lines(&stats) {
+        writeln!(out, "{}", line).map_err(write_error)?;
+    }
+    Ok(())
+}
+
+fn folder_stats_lines(stats: &FolderStats) -> Vec<String> {
+    vec![
+        format!("Name: {}", stats.file_name),
+        format!("Total size: {}", human_readable_size(stats.total_size)),
+        format!("Allocated size: {}", human_readable_size(stats.allocated_size)),
+        format!("Item count: {}", stats.item_count),
+        format!("File count: {}", stats.file_count),
+        format!("Subdirectory count: {}", stats.subdir_count),
+        format!("Inode count: {}", stats.inode_count),
+        format!("Shared via hardlinks: {}", human_readable_size(stats.shared_size)),
+        format!("Extra hardlinks skipped: {}", stats.extra_links),
+        format!("Excluded size: {}", human_readable_size(stats.excluded_size)),
+        format!("Unreadable paths: {}", stats.error_count),
+        format!(
+            "Last modified: {}",
+            stats.last_modified.map(human_readable_time).unwrap_or_else(|| "Unknown".to_string())
+        ),
+    ]
+}
+
+fn run_usage(cli: &Cli) -> Result<(), String> {
+    // (filesystem type, usage) pairs
+    let usages: Vec<(String, DriveUsage)> = match &cli.path {
+        Some(path) => {
+            let usage = drive_usage(path).map_err(|e| format!("cannot read drive usage of '{}': {}", path.display(), e))?;
+            vec![("-".to_string(), usage)]
+        }
+        None => list_mounts()
+            .map_err(|e| format!("cannot read mount table: {}", e))?
+            .into_iter()
+            // Same filter as the GUI home page: skip proc, sysfs and other pseudo filesystems
+            .filter_map(|mount| match mount.usage {
+                Some(usage) if usage.total_space > 0 => Some((mount.fs_type, usage)),
+                _ => None,
+            })
+            .collect(),
+    };
+
+    let mut out = cli.writer()?;
+    if let Some(format) = cli.format {
+        return write_drive_usage(out, usages.iter().map(|(_, usage)| usage), format).map_err(write_error);
+    }
+
+    writeln!(out, "{:<10} {:>10} {:>10} {:>10} {:>5}  Mounted on", "Type", "Size", "Used", "Avail", "Use%")
+        .map_err(write_error)?;
+    for (fs_type, usage) in &usages {
+        // One `df -h` style line
+        writeln!(
+            out,
+            "{:<10} {:>10} {:>10} {:>10} {:>4.0}%  {}",
+            fs_type,
+            human_readable_size(usage.total_space),
+            human_readable_size(usage.used_space()),
+            human_readable_size(usage.available_space),
+            usage.used_percentage(),
+            usage.mount_point.display()
+        )
+        .map_err(write_error)?;
+    }
+    Ok(())
+}
+
+fn run_ncdu(cli: &Cli) -> Result<(), String> {
+    let tree = cli.scan()?;
+    write_ncdu(cli.writer()?, &tree, tree.root()).map_err(write_error)
+}
+
+fn run_snapshot(cli: &Cli) -> Result<(), String> {
+    let output = cli.output.as_deref().ok_or("'snapshot' needs -o FILE")?;
+    let tree = cli.scan()?;
+    let snapshot = Snapshot::new(tree, cli.options.clone(), SystemTime::now());
+    save_snapshot(output, &snapshot).map_err(|e| format!("cannot save snapshot to '{}': {}", output.display(), e))
+}
+
+fn run_diff(cli: &Cli) -> Result<(), String> {
+    let old = cli.snapshot_or_scan(cli.required_path()?)?;
+    let new = cli.snapshot_or_scan(cli.other_path.as_deref().ok_or("'diff' needs an old and a new snapshot")?)?;
+    let diff = diff_snapshots(&old, &new);
+    let allocated = cli.show_allocated;
+    let mut out = cli.writer()?;
+
+    let line = |delta: &PathDelta| {
+        format!("{:>11}  {}", human_readable_delta(delta.delta(allocated)), new.root_path.join(&delta.path).display())
+    };
+    let mut report = vec![
+        format!("Old: {} ({})", old.root_path.display(), human_readable_time(old.scanned_at)),
+        format!("New: {} ({})", new.root_path.display(), human_readable_time(new.scanned_at)),
+        format!("Total change: {}", human_readable_delta(diff.root().delta(allocated))),
+        format!("Files added: {}, removed: {}", diff.added_files().count(), diff.removed_files().count()),
+        String::new(),
+        "Biggest growth:".to_string(),
+    ];
+    report.extend(diff.biggest_growth(cli.limit, allocated).into_iter().map(line));
+    report.push(String::new());
+    report.push("Largest added files:".to_string());
+    report.extend(diff.largest_added(cli.limit, allocated).into_iter().map(line));
+    report.push(String::new());
+    report.push("Largest removed files:".to_string());
+    report.extend(diff.largest_removed(cli.limit, allocated).into_iter().map(line));
+
+    for line in report {
+        writeln!(out, "{}", line).map_err(write_error)?;
+    }
+    Ok(())
+}
+
+fn run_history(cli: &Cli) -> Result<(), String> {
+    // The GUI records absolute paths, look them up the same way
+    let path = cli.required_path()?;
+    let path = path.canonicalize().map_err(|e| format!("cannot read '{}': {}", path.display(), e))?;
+    let history = History::default_location()
+        .map(|file| History::open(&file))
+        .ok_or("no data directory for the history file, set DISKSCANNER_HISTORY")?;
+
+    let mut index = history.load().map_err(|e| format!("cannot read '{}': {}", history.file().display(), e))?;
+    if cli.record {
+        let tree = scan_tree(&path, &cli.options, &ScanControl::new())
+            .ok_or_else(|| format!("cannot read '{}'", path.display()))?;
+        history
+            .record(&tree, SystemTime::now(), &mut index)
+            .map_err(|e| format!("cannot write '{}': {}", history.file().display(), e))?;
+    }
+
+    let points = index.points(&path);
+    if points.is_empty() {
+        return Err(format!("no recorded scans of '{}', run with --record first", path.display()));
+    }
+
+    let mut out = cli.writer()?;
+    let mut previous: Option<u64> = None;
+    for point in points {
+        let size = if cli.show_allocated { point.allocated_size } else { point.size };
+        let change = previous.map(|previous| human_readable_delta(size as i64 - previous as i64)).unwrap_or_default();
+        writeln!(out, "{}  {:>10}  {:>11}", human_readable_time(point.scanned_at), human_readable_size(size), change)
+            .map_err(write_error)?;
+        previous = Some(size);
+    }
+    Ok(())
+}
+
+fn run_duplicates(cli: &Cli) -> Result<(), String> {
+    let tree = cli.scan()?;
+    let duplicates = find_duplicates(&tree, tree.root(), cli.min_size, &ScanControl::new())
+        .ok_or("duplicate search was cancelled")?;
+    if !duplicates.report.is_empty() {
+        eprintln!("diskscanner-cli: {} files could not be read and were skipped", duplicates.report.len());
+    }
+
+    let mut out = cli.writer()?;
+    writeln!(
+        out,
+        "{} groups, {} files, {} reclaimable",
+        duplicates.groups.len(),
+        duplicates.file_count(),
+        human_readable_size(duplicates.reclaimable()),
+    )
+    .map_err(write_error)?;
+    for group in duplicates.groups.iter().take(cli.limit) {
+        writeln!(out, "\n{} copies of {}, {} reclaimable", group.files.len(), human_readable_size(group.size), human_readable_size(group.reclaimable()))
+            .map_err(write_error)?;
+        for file in &group.files {
+            writeln!(out, "  {}", file.display()).map_err(write_error)?;
+        }
+    }
+
+    let Some(mode) = cli.link else { return Ok(()) };
+    let (mut replaced, mut reclaimed) = (0, 0);
+    for group in &duplicates.groups {
+        let (keep, rest) = group.files.split_first().expect("duplicate groups have at least two files");
+        let report = link_duplicates(keep, rest, mode);
+        for (path, error) in &report.failed {
+            eprintln!("diskscanner-cli: cannot link '{}': {}", path.display(), error);
+        }
+        replaced += report.replaced.len();
+        reclaimed += report.reclaimed;
+    }
+    writeln!(out, "\nReplaced {} files with {}s, {} reclaimed", replaced, mode.to_string().to_lowercase(), human_readable_size(reclaimed))
+        .map_err(write_error)?;
+    Ok(())
+}
+
+fn run_empty(cli: &Cli) -> Result<(), String> {
+    let tree = cli.scan()?;
+    let empty = find_empty(&tree, tree.root());
+    let mut out = cli.writer()?;
+    for (title, paths) in [("Empty folders", &empty.folders), ("Zero-byte files", &empty.files)] {
+        writeln!(out, "{} ({}):", title, paths.len()).map_err(write_error)?;
+        for path in paths {
+            writeln!(out, "  {}", path.display()).map_err(write_error)?;
+        }
+    }
+    Ok(())
+}
+
+fn run_symlinks(cli: &Cli) -> Result<(), String> {
+    let tree = cli.scan()?;
+    let links = find_symlinks(&tree, tree.root());
+    let mut out = cli.writer()?;
+    for link in &links {
+        writeln!(out, "{:<10} {} -> {}", link.status.to_string(), link.path.display(), link.target.display()).map_err(write_error)?;
+    }
+    let broken = links.iter().filter(|link| link.is_broken()).count();
+    writeln!(out, "\n{} symlinks, {} broken", links.len(), broken).map_err(write_error)?;
+    Ok(())
+}
+
+fn write_error(error: io::Error) -> String {
+    format!("cannot write output: {}", error)
+}
@@ -0,0 +1,155 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use chrono::{DateTime, Utc};
+use iced::mouse;
+use iced::widget::canvas::{self, Frame, Geometry, Path, Stroke, Text};
+use iced::{Color, Point, Rectangle, Renderer, Size, Theme};
+use DiskScanner::scanner::HistoryPoint;
+use DiskScanner::scanner::utils::{human_readable_size, human_readable_time};
+
+// Room for the size labels on the left and the dates below the plot
+const LEFT_MARGIN: f32 = 80.0;
+const BOTTOM_MARGIN: f32 = 24.0;
+const PADDING: f32 = 10.0;
+
+// Size of one folder over every scan in the history, oldest on the left
+pub struct GrowthChart {
+    pub points: Vec<HistoryPoint>,
+    pub allocated: bool, // plot disk usage instead of apparent size
+}
+
+impl GrowthChart {
+    fn value(&self, point: &HistoryPoint) -> u64 {
+        if self.allocated { point.allocated_size } else { point.size }
+    }
+
+    // Where every point lands inside `plot`
+    fn positions(&self, plot: Rectangle) -> Vec<Point> {
+        let seconds = |point: &HistoryPoint| {
+            point.scanned_at.duration_since(UNIX_EPOCH).map(|d| d.as_secs_f64()).unwrap_or(0.0)
+        };
+        let (first, last) = match (self.points.first(), self.points.last()) {
+            (Some(first), Some(last)) => (seconds(first), seconds(last)),
+            _ => return Vec::new(),
+        };
+        let (low, high) = self.value_range();
+
+        self.points
+            .iter()
+            .map(|point| {
+                let x = if last > first { (seconds(point) - first) / (last - first) } else { 0.5 };
+                let y = (self.value(point) - low) as f64 / (high - low) as f64;
+                Point::new(plot.x + x as f32 * plot.width, plot.y + plot.height - y as f32 * plot.height)
+            })
+            .collect()
+    }
+
+    // Smallest and largest value, never the same so a flat line sits in the middle
+    fn value_range(&self) -> (u64, u64) {
+        let low = self.points.iter().map(|point| self.value(point)).min().unwrap_or(0);
+        let high = self.points.iter().map(|point| self.value(point)).max().unwrap_or(0);
+        if low == high {
+            (low.saturating_sub(1), high + 1)
+        } else {
+            (low, high)
+        }
+    }
+}
+
+impl<Message> canvas::Program<Message> for GrowthChart {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> Vec<Geometry<Renderer>> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let palette = theme.extended_palette();
+        let text_color = palette.background.base.text;
+        let axis_color = Color { a: 0.4, ..text_color };
+
+        let plot = Rectangle::new(
+            Point::new(LEFT_MARGIN, PADDING),
+            Size::new(
+                (bounds.width - LEFT_MARGIN - PADDING).max(1.0),
+                (bounds.height - BOTTOM_MARGIN - PADDING).max(1.0),
+            ),
+        );
+
+        let axes = Path::new(|builder| {
+            builder.move_to(Point::new(plot.x, plot.y));
+            builder.line_to(Point::new(plot.x, plot.y + plot.height));
+            builder.line_to(Point::new(plot.x + plot.width, plot.y + plot.height));
+        });
+        frame.stroke(&axes, Stroke::default().with_color(axis_color).with_width(1.0));
+
+        let label = |content: String, position: Point| Text {
+            content,
+            position,
+            color: text_color,
+            size: 12.into(),
+            ..Text::default()
+        };
+
+        // Size labels at the top and bottom of the value range
+        let (low, high) = self.value_range();
+        frame.fill_text(label(human_readable_size(high), Point::new(PADDING, plot.y)));
+        frame.fill_text(label(human_readable_size(low), Point::new(PADDING, plot.y + plot.height - 12.0)));
+
+        // Dates of the first and last scan under the x axis
+        let date = |time: SystemTime| DateTime::<Utc>::from(time).format("%d/%m/%Y").to_string();
+        if let (Some(first), Some(last)) = (self.points.first(), self.points.last()) {
+            let below_axis = plot.y + plot.height + 6.0;
+            frame.fill_text(label(date(first.scanned_at), Point::new(plot.x, below_axis)));
+            if self.points.len() > 1 {
+                frame.fill_text(Text {
+                    horizontal_alignment: iced::alignment::Horizontal::Right,
+                    ..label(date(last.scanned_at), Point::new(plot.x + plot.width, below_axis))
+                });
+            }
+        }
+
+        let positions = self.positions(plot);
+        let line_color = palette.primary.strong.color;
+        if positions.len() > 1 {
+            let line = Path::new(|builder| {
+                builder.move_to(positions[0]);
+                for &position in &positions[1..] {
+                    builder.line_to(position);
+                }
+            });
+            frame.stroke(&line, Stroke::default().with_color(line_color).with_width(2.0));
+        }
+        for &position in &positions {
+            frame.fill(&Path::circle(position, 3.0), line_color);
+        }
+
+        // The scan closest to the cursor, with its exact size and time
+        if let Some(cursor) = cursor.position_in(bounds) {
+            let nearest = positions
+                .iter()
+                .enumerate()
+                .min_by(|(_, a), (_, b)| (a.x - cursor.x).abs().total_cmp(&(b.x - cursor.x).abs()));
+            if let Some((index, &position)) = nearest {
+                let point = &self.points[index];
+                frame.fill(&Path::circle(position, 5.0), line_color);
+                let tooltip = format!("{}  {}", human_readable_size(self.value(point)), human_readable_time(point.scanned_at));
+                // Keep the text inside the canvas on the right hand side
+                let align_x = if position.x > plot.x + plot.width / 2.0 {
+                    iced::alignment::Horizontal::Right
+                } else {
+                    iced::alignment::Horizontal::Left
+                };
+                frame.fill_text(Text {
+                    horizontal_alignment: align_x,
+                    ..label(tooltip, Point::new(position.x, (position.y - 20.0).max(0.0)))
+                });
+            }
+        }
+
+        vec![frame.into_geometry()]
+    }
+}
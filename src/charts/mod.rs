@@ -0,0 +1,85 @@
+// Canvas views of the scanned tree for the GUI
+mod growth;
+mod sunburst;
+mod treemap;
+
+pub use growth::GrowthChart;
+pub use sunburst::Sunburst;
+pub use treemap::Treemap;
+
+use std::cell::RefCell;
+use iced::widget::canvas::{self, Frame, Text};
+use iced::{Color, Point, Size};
+use DiskScanner::scanner::{FileEntry, FileKind};
+
+// What the treemap and sunburst drew last, and the layout behind it for hovering and clicking.
+// Kept until `clear`, which the app calls when the tree, the folder shown, the size mode or the view changes;
+// the geometry and the treemap's tiles are also redone when the canvas changes size.
+#[derive(Default)]
+pub struct ChartCache {
+    geometry: canvas::Cache,
+    tiles: RefCell<Option<(Size, Vec<treemap::Tile>)>>,
+    segments: RefCell<Option<Vec<sunburst::Segment>>>,
+}
+
+impl ChartCache {
+    pub fn clear(&self) {
+        self.geometry.clear();
+        self.tiles.take();
+        self.segments.take();
+    }
+}
+
+// A copy starts out empty, it gets filled on its first draw
+impl Clone for ChartCache {
+    fn clone(&self) -> Self {
+        ChartCache::default()
+    }
+}
+
+// Fill color of a file or folder, files grouped by what their extension says they are
+fn entry_color(entry: &FileEntry) -> Color {
+    if entry.is_folder {
+        return Color::from_rgb(0.55, 0.60, 0.68);
+    }
+    if entry.file_type != FileKind::File {
+        return Color::from_rgb(0.60, 0.60, 0.60);
+    }
+
+    let extension = entry.path.extension().map(|ext| ext.to_string_lossy().to_lowercase()).unwrap_or_default();
+    match extension.as_str() {
+        "png" | "jpg" | "jpeg" | "gif" | "bmp" | "webp" | "svg" | "ico" | "tif" | "tiff" | "heic" | "raw" => {
+            Color::from_rgb(0.93, 0.62, 0.27) // images
+        }
+        "mp4" | "mkv" | "avi" | "mov" | "webm" | "wmv" | "flv" | "m4v" => Color::from_rgb(0.86, 0.36, 0.36), // video
+        "mp3" | "flac" | "wav" | "ogg" | "m4a" | "aac" | "opus" => Color::from_rgb(0.73, 0.45, 0.85), // audio
+        "zip" | "tar" | "gz" | "xz" | "bz2" | "zst" | "7z" | "rar" | "iso" | "dmg" | "deb" | "rpm" => {
+            Color::from_rgb(0.85, 0.78, 0.30) // archives and disk images
+        }
+        "pdf" | "doc" | "docx" | "odt" | "xls" | "xlsx" | "ods" | "ppt" | "pptx" | "txt" | "md" | "csv" | "epub" => {
+            Color::from_rgb(0.40, 0.66, 0.88) // documents
+        }
+        "rs" | "c" | "h" | "cpp" | "hpp" | "py" | "js" | "ts" | "go" | "java" | "html" | "css" | "json" | "toml"
+        | "yaml" | "yml" | "xml" | "sh" => Color::from_rgb(0.45, 0.78, 0.50), // source and config
+        "exe" | "dll" | "so" | "dylib" | "a" | "o" | "rlib" | "bin" | "wasm" => Color::from_rgb(0.55, 0.50, 0.80), // binaries
+        _ => Color::from_rgb(0.70, 0.72, 0.74),
+    }
+}
+
+// A dark box with one line of text next to the cursor, kept inside the canvas
+fn draw_tooltip(frame: &mut Frame, bounds: Size, cursor: Point, label: String) {
+    // Rough width, canvas text can't be measured before it is drawn
+    let width = (label.chars().count() as f32 * 7.0 + 12.0).min(bounds.width);
+    let height = 22.0;
+    let x = (cursor.x + 12.0).min(bounds.width - width).max(0.0);
+    let y = if cursor.y + 20.0 + height > bounds.height { cursor.y - 10.0 - height } else { cursor.y + 20.0 };
+
+    frame.fill_rectangle(Point::new(x, y), Size::new(width, height), Color::from_rgba(0.0, 0.0, 0.0, 0.8));
+    frame.fill_text(Text {
+        content: label,
+        position: Point::new(x + 6.0, y + 4.0),
+        color: Color::WHITE,
+        size: 13.into(),
+        ..Text::default()
+    });
+}
@@ -0,0 +1,358 @@
+use std::f32::consts::{FRAC_PI_2, TAU};
+use std::path::PathBuf;
+use iced::mouse;
+use iced::widget::canvas::{self, event, Event, Frame, Geometry, Path, Stroke, Text};
+use iced::{Color, Point, Rectangle, Renderer, Size, Theme, Vector};
+use DiskScanner::scanner::{DirTree, NodeId};
+use DiskScanner::scanner::utils::human_readable_size;
+use super::{draw_tooltip, entry_color, ChartCache};
+
+// Rings drawn around the center, one per folder level
+const LEVELS: usize = 4;
+// Segments thinner than this (in radians) are too small to see or click
+const MIN_SWEEP: f32 = 0.004;
+
+// Rings of the subtree under `root`, the center being `root` itself.
+// Clicking a segment sends `on_click` with its path, clicking the center sends `on_center`.
+pub struct Sunburst<'a, Message> {
+    pub tree: &'a DirTree,
+    pub root: NodeId,
+    pub allocated: bool, // segment angles from disk usage instead of apparent size
+    pub on_click: fn(PathBuf) -> Message,
+    pub on_center: Option<Message>, // None at the scan root, there is nothing to zoom out to
+    pub cache: &'a ChartCache,
+}
+
+pub(super) struct Segment {
+    id: NodeId,
+    ring: usize, // 1 for the root's children
+    start: f32,  // radians, clockwise from 12 o'clock
+    end: f32,
+    branch: usize, // which of the root's children it is under, for its color
+}
+
+// Center and radii of the chart inside the canvas
+struct Rings {
+    center: Point,
+    hole: f32,  // radius of the center label
+    width: f32, // of one ring
+}
+
+enum Hit<'a> {
+    Center,
+    Segment(&'a Segment),
+}
+
+impl Rings {
+    fn new(bounds: Size) -> Rings {
+        let radius = (bounds.width.min(bounds.height) / 2.0 - 4.0).max(1.0);
+        let hole = radius * 0.25;
+        Rings {
+            center: Point::new(bounds.width / 2.0, bounds.height / 2.0),
+            hole,
+            width: (radius - hole) / LEVELS as f32,
+        }
+    }
+
+    fn inner(&self, ring: usize) -> f32 {
+        self.hole + (ring - 1) as f32 * self.width
+    }
+
+    fn point(&self, radius: f32, angle: f32) -> Point {
+        // Angle 0 is straight up
+        let angle = angle - FRAC_PI_2;
+        self.center + Vector::new(radius * angle.cos(), radius * angle.sin())
+    }
+}
+
+impl<Message: Clone> Sunburst<'_, Message> {
+    fn size(&self, id: NodeId) -> u64 {
+        let node = self.tree.node(id);
+        if self.allocated { node.allocated_size } else { node.total_size }
+    }
+
+    // Angles don't depend on the canvas size, the cached segments stay until the cache is cleared
+    fn with_segments<R>(&self, f: impl FnOnce(&[Segment]) -> R) -> R {
+        let mut cached = self.cache.segments.borrow_mut();
+        f(cached.get_or_insert_with(|| self.layout()))
+    }
+
+    // Every segment, parents before their children
+    fn layout(&self) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        self.layout_children(self.root, 0.0, TAU, 1, None, &mut segments);
+        segments
+    }
+
+    fn layout_children(&self, folder: NodeId, start: f32, end: f32, ring: usize, branch: Option<usize>, segments: &mut Vec<Segment>) {
+        // Angles are shares of the folder's own total, so whatever the folder itself takes shows as a gap
+        let total = self.size(folder);
+        if total == 0 {
+            return;
+        }
+        let mut children: Vec<(NodeId, u64)> = self.tree.children(folder).iter().map(|&id| (id, self.size(id))).collect();
+        children.sort_by_key(|&(_, size)| std::cmp::Reverse(size));
+
+        let mut angle = start;
+        for (index, (id, size)) in children.into_iter().enumerate() {
+            let sweep = (end - start) * (size as f64 / total as f64) as f32;
+            if sweep < MIN_SWEEP {
+                // Sorted largest first, everything after this is smaller still
+                break;
+            }
+            let branch = branch.unwrap_or(index);
+            segments.push(Segment { id, ring, start: angle, end: angle + sweep, branch });
+            if ring < LEVELS && self.tree.node(id).entry.is_folder {
+                self.layout_children(id, angle, angle + sweep, ring + 1, Some(branch), segments);
+            }
+            angle += sweep;
+        }
+    }
+
+    fn hit<'s>(segments: &'s [Segment], rings: &Rings, position: Point) -> Option<Hit<'s>> {
+        let offset = position - rings.center;
+        let distance = (offset.x * offset.x + offset.y * offset.y).sqrt();
+        if distance < rings.hole {
+            return Some(Hit::Center);
+        }
+        let ring = ((distance - rings.hole) / rings.width) as usize + 1;
+        let angle = (offset.y.atan2(offset.x) + FRAC_PI_2).rem_euclid(TAU);
+        segments
+            .iter()
+            .find(|segment| segment.ring == ring && segment.start <= angle && angle < segment.end)
+            .map(Hit::Segment)
+    }
+
+    fn color(&self, segment: &Segment) -> Color {
+        let entry = &self.tree.node(segment.id).entry;
+        if !entry.is_folder {
+            return entry_color(entry);
+        }
+        // Golden angle steps keep neighbouring branches apart, deeper rings get lighter
+        let hue = (segment.branch as f32 * 137.5) % 360.0;
+        let lightness = 0.45 + 0.08 * (segment.ring - 1) as f32;
+        hsl(hue, 0.55, lightness)
+    }
+}
+
+impl<Message: Clone> canvas::Program<Message> for Sunburst<'_, Message> {
+    type State = ();
+
+    fn update(
+        &self,
+        _state: &mut Self::State,
+        event: Event,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> (event::Status, Option<Message>) {
+        let Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) = event else {
+            return (event::Status::Ignored, None);
+        };
+        let Some(position) = cursor.position_in(bounds) else {
+            return (event::Status::Ignored, None);
+        };
+
+        // Some(None) for the center
+        let hit = self.with_segments(|segments| match Self::hit(segments, &Rings::new(bounds.size()), position)? {
+            Hit::Center => Some(None),
+            Hit::Segment(segment) => Some(Some(segment.id)),
+        });
+        match hit {
+            Some(None) => (event::Status::Captured, self.on_center.clone()),
+            Some(Some(id)) => {
+                let path = self.tree.path(id).to_path_buf();
+                (event::Status::Captured, Some((self.on_click)(path)))
+            }
+            None => (event::Status::Ignored, None),
+        }
+    }
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> Vec<Geometry<Renderer>> {
+        let palette = theme.extended_palette();
+        let border = palette.background.base.color;
+        let rings = Rings::new(bounds.size());
+
+        self.with_segments(|segments| {
+            // The rings only change with the tree or the canvas size, the center and the hover highlight go on top
+            let chart = self.cache.geometry.draw(renderer, bounds.size(), |frame| {
+                for segment in segments {
+                    let path = ring_segment(&rings, segment);
+                    frame.fill(&path, self.color(segment));
+                    frame.stroke(&path, Stroke::default().with_color(border).with_width(1.0));
+                }
+            });
+
+            let mut overlay = Frame::new(renderer, bounds.size());
+            let hovered = cursor.position_in(bounds).and_then(|position| Some((position, Self::hit(segments, &rings, position)?)));
+
+            // The current folder in the middle, highlighted when clicking it zooms out
+            let center_color = match hovered {
+                Some((_, Hit::Center)) if self.on_center.is_some() => palette.primary.weak.color,
+                _ => palette.background.weak.color,
+            };
+            overlay.fill(&Path::circle(rings.center, rings.hole), center_color);
+            let label = |content: String, offset: f32, size: f32| Text {
+                content,
+                position: rings.center + Vector::new(0.0, offset),
+                color: palette.background.base.text,
+                size: size.into(),
+                horizontal_alignment: iced::alignment::Horizontal::Center,
+                vertical_alignment: iced::alignment::Vertical::Center,
+                ..Text::default()
+            };
+            // Long names are cut to what roughly fits in the hole
+            let name = &self.tree.node(self.root).entry.name;
+            let fits = ((rings.hole * 2.0) / 7.5).max(4.0) as usize;
+            let name = if name.chars().count() > fits {
+                format!("{}…", name.chars().take(fits.saturating_sub(1)).collect::<String>())
+            } else {
+                name.clone()
+            };
+            overlay.fill_text(label(name, -9.0, 14.0));
+            overlay.fill_text(label(human_readable_size(self.size(self.root)), 9.0, 13.0));
+
+            if let Some((position, hit)) = hovered {
+                let id = match hit {
+                    Hit::Center => self.root,
+                    Hit::Segment(segment) => {
+                        let path = ring_segment(&rings, segment);
+                        overlay.stroke(&path, Stroke::default().with_color(Color::WHITE).with_width(2.0));
+                        segment.id
+                    }
+                };
+                let label = format!("{}  {}", self.tree.path(id).display(), human_readable_size(self.size(id)));
+                draw_tooltip(&mut overlay, bounds.size(), position, label);
+            }
+
+            vec![chart, overlay.into_geometry()]
+        })
+    }
+
+    fn mouse_interaction(&self, _state: &Self::State, bounds: Rectangle, cursor: mouse::Cursor) -> mouse::Interaction {
+        if cursor.is_over(bounds) {
+            mouse::Interaction::Pointer
+        } else {
+            mouse::Interaction::default()
+        }
+    }
+}
+
+// One ring segment as a closed outline: the outer arc forwards, the inner one back.
+// Path::arc starts a new sub-path, so both arcs are sampled into line segments instead.
+fn ring_segment(rings: &Rings, segment: &Segment) -> Path {
+    let inner = rings.inner(segment.ring);
+    let outer = inner + rings.width;
+    let steps = ((segment.end - segment.start) / 0.05).ceil().max(1.0) as usize;
+    let angle = |step: usize| segment.start + (segment.end - segment.start) * step as f32 / steps as f32;
+
+    Path::new(|builder| {
+        builder.move_to(rings.point(outer, segment.start));
+        for step in 1..=steps {
+            builder.line_to(rings.point(outer, angle(step)));
+        }
+        for step in (0..=steps).rev() {
+            builder.line_to(rings.point(inner, angle(step)));
+        }
+        builder.close();
+    })
+}
+
+// hue in degrees, saturation and lightness in 0..1
+fn hsl(hue: f32, saturation: f32, lightness: f32) -> Color {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let sector = hue / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    Color::from_rgb(r + m, g + m, b + m)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::TAU;
+    use std::path::{Path, PathBuf};
+    use iced::Size;
+    use DiskScanner::scanner::{read_ncdu, DirTree, NodeId};
+    use super::super::ChartCache;
+    use super::{Hit, Rings, Sunburst};
+
+    // 1000 bytes: big 600, mid 300 (x 200, y 100), small 100, and an empty file with no segment
+    fn tree() -> DirTree {
+        let export = r#"[1, 2, {}, [{"name": "/root"},
+            {"name": "big", "asize": 600},
+            [{"name": "mid"}, {"name": "x", "asize": 200}, {"name": "y", "asize": 100}],
+            {"name": "small", "asize": 100},
+            {"name": "nothing", "asize": 0}
+        ]]"#;
+        read_ncdu(export.as_bytes()).unwrap()
+    }
+
+    fn sunburst<'a>(tree: &'a DirTree, cache: &'a ChartCache) -> Sunburst<'a, PathBuf> {
+        Sunburst { tree, root: tree.root(), allocated: false, on_click: |path| path, on_center: None, cache }
+    }
+
+    fn name(tree: &DirTree, id: NodeId) -> &str {
+        &tree.node(id).entry.name
+    }
+
+    #[test]
+    fn segments_share_out_their_parent() {
+        let tree = tree();
+        let cache = ChartCache::default();
+        let segments = sunburst(&tree, &cache).layout();
+
+        let names: Vec<(&str, usize)> = segments.iter().map(|segment| (name(&tree, segment.id), segment.ring)).collect();
+        assert_eq!(names, [("big", 1), ("mid", 1), ("x", 2), ("y", 2), ("small", 1)]);
+
+        let ring_one: f32 = segments.iter().filter(|segment| segment.ring == 1).map(|segment| segment.end - segment.start).sum();
+        // The root has no bytes of its own, so the first ring closes without a gap
+        assert!(ring_one <= TAU + 1e-4 && ring_one > TAU - 1e-4);
+
+        // Children stay inside their folder's sweep, in proportion to it
+        let mid = &segments[1];
+        for child in &segments[2..4] {
+            assert!(child.start >= mid.start - 1e-4 && child.end <= mid.end + 1e-4);
+            assert_eq!(child.branch, mid.branch);
+        }
+        assert!(((segments[2].end - segments[2].start) - 0.2 * TAU).abs() < 1e-4);
+    }
+
+    #[test]
+    fn hits_map_angle_and_radius_to_a_segment() {
+        let tree = tree();
+        let cache = ChartCache::default();
+        let chart = sunburst(&tree, &cache);
+        let segments = chart.layout();
+        let rings = Rings::new(Size::new(200.0, 200.0));
+        let ring_middle = |ring: usize| rings.inner(ring) + rings.width / 2.0;
+        let hit = |radius: f32, turn: f32| match Sunburst::<PathBuf>::hit(&segments, &rings, rings.point(radius, turn * TAU)) {
+            Some(Hit::Center) => Some(Path::new("center").to_path_buf()),
+            Some(Hit::Segment(segment)) => Some(tree.path(segment.id).to_path_buf()),
+            None => None,
+        };
+
+        assert_eq!(hit(rings.hole / 2.0, 0.5), Some(PathBuf::from("center")));
+        assert_eq!(hit(ring_middle(1), 0.3), Some(PathBuf::from("/root/big")));
+        assert_eq!(hit(ring_middle(1), 0.75), Some(PathBuf::from("/root/mid")));
+        assert_eq!(hit(ring_middle(1), 0.95), Some(PathBuf::from("/root/small")));
+        assert_eq!(hit(ring_middle(2), 0.7), Some(PathBuf::from("/root/mid/x")));
+        assert_eq!(hit(ring_middle(2), 0.85), Some(PathBuf::from("/root/mid/y")));
+        // Nothing outside mid on the second ring, and nothing past the last ring
+        assert_eq!(hit(ring_middle(2), 0.3), None);
+        assert_eq!(hit(ring_middle(5), 0.3), None);
+    }
+}
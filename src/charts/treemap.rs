@@ -0,0 +1,300 @@
+use std::path::PathBuf;
+use iced::mouse;
+use iced::widget::canvas::{self, event, Event, Frame, Geometry, Stroke, Text};
+use iced::{Color, Point, Rectangle, Renderer, Size, Theme};
+use DiskScanner::scanner::{DirTree, NodeId};
+use DiskScanner::scanner::utils::human_readable_size;
+use super::{draw_tooltip, entry_color, ChartCache};
+
+// How many folder levels below the current one get their own tiles
+const MAX_DEPTH: usize = 4;
+// Folders smaller than this are drawn as one tile instead of being split further
+const MIN_SPLIT: f32 = 24.0;
+const HEADER: f32 = 16.0;
+const INSET: f32 = 2.0;
+
+// Squarified treemap of the subtree under `root`, clicking a tile sends `on_click` with its path
+pub struct Treemap<'a, Message> {
+    pub tree: &'a DirTree,
+    pub root: NodeId,
+    pub allocated: bool, // tile areas from disk usage instead of apparent size
+    pub on_click: fn(PathBuf) -> Message,
+    pub cache: &'a ChartCache,
+}
+
+pub(super) struct Tile {
+    id: NodeId,
+    rect: Rectangle,
+    depth: usize,
+}
+
+impl<Message> Treemap<'_, Message> {
+    fn size(&self, id: NodeId) -> u64 {
+        let node = self.tree.node(id);
+        if self.allocated { node.allocated_size } else { node.total_size }
+    }
+
+    // The cached tiles for a canvas of `bounds`, laid out again when there are none for that size
+    fn with_tiles<R>(&self, bounds: Size, f: impl FnOnce(&[Tile]) -> R) -> R {
+        let mut cached = self.cache.tiles.borrow_mut();
+        if !cached.as_ref().is_some_and(|(size, _)| *size == bounds) {
+            *cached = Some((bounds, self.layout(bounds)));
+        }
+        f(cached.as_ref().map(|(_, tiles)| tiles.as_slice()).unwrap_or_default())
+    }
+
+    // Every tile in drawing order, parents before their children
+    fn layout(&self, bounds: Size) -> Vec<Tile> {
+        let mut tiles = Vec::new();
+        self.layout_children(self.root, Rectangle::new(Point::ORIGIN, bounds), 0, &mut tiles);
+        tiles
+    }
+
+    fn layout_children(&self, folder: NodeId, area: Rectangle, depth: usize, tiles: &mut Vec<Tile>) {
+        let mut children: Vec<(NodeId, u64)> = self.tree
+            .children(folder)
+            .iter()
+            .map(|&id| (id, self.size(id)))
+            .filter(|&(_, size)| size > 0)
+            .collect();
+        children.sort_by_key(|&(_, size)| std::cmp::Reverse(size));
+
+        let sizes: Vec<f64> = children.iter().map(|&(_, size)| size as f64).collect();
+        for ((id, _), rect) in children.into_iter().zip(squarify(&sizes, area)) {
+            tiles.push(Tile { id, rect, depth });
+
+            let node = self.tree.node(id);
+            if node.entry.is_folder && depth + 1 < MAX_DEPTH && rect.width > MIN_SPLIT && rect.height > MIN_SPLIT + HEADER {
+                // Leave room for the folder's name above its contents
+                let inner = Rectangle::new(
+                    Point::new(rect.x + INSET, rect.y + HEADER),
+                    Size::new(rect.width - 2.0 * INSET, rect.height - HEADER - INSET),
+                );
+                self.layout_children(id, inner, depth + 1, tiles);
+            }
+        }
+    }
+
+    // The deepest tile under `position`
+    fn tile_at(tiles: &[Tile], position: Point) -> Option<&Tile> {
+        tiles.iter().rev().find(|tile| tile.rect.contains(position))
+    }
+}
+
+impl<Message> canvas::Program<Message> for Treemap<'_, Message> {
+    type State = ();
+
+    fn update(
+        &self,
+        _state: &mut Self::State,
+        event: Event,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> (event::Status, Option<Message>) {
+        let Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) = event else {
+            return (event::Status::Ignored, None);
+        };
+        let Some(position) = cursor.position_in(bounds) else {
+            return (event::Status::Ignored, None);
+        };
+
+        match self.with_tiles(bounds.size(), |tiles| Self::tile_at(tiles, position).map(|tile| tile.id)) {
+            Some(id) => {
+                let path = self.tree.path(id).to_path_buf();
+                (event::Status::Captured, Some((self.on_click)(path)))
+            }
+            None => (event::Status::Ignored, None),
+        }
+    }
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        theme: &Theme,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> Vec<Geometry<Renderer>> {
+        let palette = theme.extended_palette();
+        let border = palette.background.base.color;
+
+        self.with_tiles(bounds.size(), |tiles| {
+            // The tiles only change with the tree or the canvas size, hovering just redraws the highlight on top
+            let chart = self.cache.geometry.draw(renderer, bounds.size(), |frame| {
+                if tiles.is_empty() {
+                    frame.fill_text(Text {
+                        content: "Nothing to show, this folder is empty".to_string(),
+                        position: Point::new(10.0, 10.0),
+                        color: palette.background.base.text,
+                        ..Text::default()
+                    });
+                    return;
+                }
+
+                for tile in tiles {
+                    let entry = &self.tree.node(tile.id).entry;
+                    // Deeper folders get a little lighter so the nesting stays readable
+                    let color = entry_color(entry);
+                    let lighten = 0.08 * tile.depth as f32;
+                    let color = Color::from_rgb(
+                        color.r + (1.0 - color.r) * lighten,
+                        color.g + (1.0 - color.g) * lighten,
+                        color.b + (1.0 - color.b) * lighten,
+                    );
+                    frame.fill_rectangle(tile.rect.position(), tile.rect.size(), color);
+                    frame.stroke_rectangle(tile.rect.position(), tile.rect.size(), Stroke::default().with_color(border).with_width(1.0));
+
+                    if tile.rect.width > 40.0 && tile.rect.height > HEADER {
+                        frame.with_clip(tile.rect, |frame| {
+                            frame.fill_text(Text {
+                                content: entry.name.clone(),
+                                position: Point::new(3.0, 2.0),
+                                color: Color::BLACK,
+                                size: 12.into(),
+                                ..Text::default()
+                            });
+                        });
+                    }
+                }
+            });
+
+            let mut overlay = Frame::new(renderer, bounds.size());
+            if let Some(position) = cursor.position_in(bounds) {
+                if let Some(tile) = Self::tile_at(tiles, position) {
+                    overlay.stroke_rectangle(
+                        tile.rect.position(),
+                        tile.rect.size(),
+                        Stroke::default().with_color(Color::WHITE).with_width(2.0),
+                    );
+                    let label = format!("{}  {}", self.tree.path(tile.id).display(), human_readable_size(self.size(tile.id)));
+                    draw_tooltip(&mut overlay, bounds.size(), position, label);
+                }
+            }
+
+            vec![chart, overlay.into_geometry()]
+        })
+    }
+
+    fn mouse_interaction(&self, _state: &Self::State, bounds: Rectangle, cursor: mouse::Cursor) -> mouse::Interaction {
+        if cursor.is_over(bounds) {
+            mouse::Interaction::Pointer
+        } else {
+            mouse::Interaction::default()
+        }
+    }
+}
+
+// Squarified layout (Bruls, Huizing, van Wijk): tiles with aspect ratios close to 1,
+// in the order of `sizes`, which should be sorted largest first
+fn squarify(sizes: &[f64], area: Rectangle) -> Vec<Rectangle> {
+    let total: f64 = sizes.iter().sum();
+    if total <= 0.0 || area.width <= 0.0 || area.height <= 0.0 {
+        return vec![Rectangle::new(area.position(), Size::ZERO); sizes.len()];
+    }
+    // Work in pixels squared from here on
+    let scale = (area.width * area.height) as f64 / total;
+    let areas: Vec<f64> = sizes.iter().map(|size| size * scale).collect();
+
+    let mut tiles = Vec::with_capacity(areas.len());
+    let mut free = area;
+    let mut start = 0;
+    while start < areas.len() {
+        let side = free.width.min(free.height) as f64;
+
+        // Grow the row while that keeps its worst aspect ratio from getting worse
+        let mut end = start + 1;
+        while end < areas.len() && worst_ratio(&areas[start..=end], side) <= worst_ratio(&areas[start..end], side) {
+            end += 1;
+        }
+
+        let row = &areas[start..end];
+        let row_area: f64 = row.iter().sum();
+        if free.width >= free.height {
+            // A column along the left edge
+            let width = (row_area / free.height as f64) as f32;
+            let mut y = free.y;
+            for &tile in row {
+                let height = (tile / width as f64) as f32;
+                tiles.push(Rectangle::new(Point::new(free.x, y), Size::new(width, height)));
+                y += height;
+            }
+            free = Rectangle::new(Point::new(free.x + width, free.y), Size::new((free.width - width).max(0.0), free.height));
+        } else {
+            // A row along the top edge
+            let height = (row_area / free.width as f64) as f32;
+            let mut x = free.x;
+            for &tile in row {
+                let width = (tile / height as f64) as f32;
+                tiles.push(Rectangle::new(Point::new(x, free.y), Size::new(width, height)));
+                x += width;
+            }
+            free = Rectangle::new(Point::new(free.x, free.y + height), Size::new(free.width, (free.height - height).max(0.0)));
+        }
+        start = end;
+    }
+    tiles
+}
+
+// Worst aspect ratio of a row of tile areas laid along a side of length `side`
+fn worst_ratio(row: &[f64], side: f64) -> f64 {
+    let sum: f64 = row.iter().sum();
+    let largest = row.iter().cloned().fold(f64::MIN, f64::max);
+    let smallest = row.iter().cloned().fold(f64::MAX, f64::min);
+    let side = side * side;
+    let sum = sum * sum;
+    (side * largest / sum).max(sum / (side * smallest))
+}
+
+#[cfg(test)]
+mod tests {
+    use iced::{Point, Rectangle, Size};
+    use super::squarify;
+
+    #[test]
+    fn tiles_fill_the_area_in_proportion() {
+        let area = Rectangle::new(Point::new(10.0, 20.0), Size::new(600.0, 400.0));
+        let sizes = [6.0, 6.0, 4.0, 3.0, 2.0, 2.0, 1.0];
+        let tiles = squarify(&sizes, area);
+        assert_eq!(tiles.len(), sizes.len());
+
+        let total: f64 = sizes.iter().sum();
+        let mut covered = 0.0;
+        for (tile, size) in tiles.iter().zip(sizes) {
+            let tile_area = (tile.width * tile.height) as f64;
+            covered += tile_area;
+            assert!((tile_area - size / total * 240_000.0).abs() < 1.0, "{:?}", tile);
+            assert!(tile.x >= area.x - 0.01 && tile.y >= area.y - 0.01);
+            assert!(tile.x + tile.width <= area.x + area.width + 0.01);
+            assert!(tile.y + tile.height <= area.y + area.height + 0.01);
+        }
+        assert!((covered - 240_000.0).abs() < 1.0);
+
+        // No two tiles overlap
+        for (i, a) in tiles.iter().enumerate() {
+            for b in &tiles[i + 1..] {
+                let width = (a.x + a.width).min(b.x + b.width) - a.x.max(b.x);
+                let height = (a.y + a.height).min(b.y + b.height) - a.y.max(b.y);
+                assert!(width <= 0.01 || height <= 0.01, "{:?} overlaps {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn tiles_stay_close_to_square() {
+        let tiles = squarify(&[1.0; 16], Rectangle::new(Point::ORIGIN, Size::new(400.0, 400.0)));
+        for tile in tiles {
+            let ratio = tile.width.max(tile.height) / tile.width.min(tile.height);
+            assert!(ratio < 2.0, "{:?}", tile);
+        }
+    }
+
+    #[test]
+    fn nothing_to_lay_out_gives_empty_tiles() {
+        let area = Rectangle::new(Point::new(5.0, 5.0), Size::new(100.0, 50.0));
+        for tile in squarify(&[0.0, 0.0], area) {
+            assert_eq!(tile, Rectangle::new(Point::new(5.0, 5.0), Size::ZERO));
+        }
+        assert_eq!(squarify(&[3.0], Rectangle::new(Point::ORIGIN, Size::ZERO)).len(), 1);
+        assert!(squarify(&[], area).is_empty());
+    }
+}
@@ -1 +1,3 @@
+#![allow(non_snake_case)]
+
 pub mod scanner;
\ No newline at end of file
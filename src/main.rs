@@ -1,39 +1,87 @@
-use std::path::PathBuf;
-use open;
-use std::fs;
-use std::time::Instant;
+#![allow(non_snake_case)]
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
 use iced::widget::image;
-use iced::widget::image::Handle;
 use iced::widget::{
-    button, center, checkbox,Column, Container, Row, column, horizontal_rule,
+    button, canvas, checkbox, column, container, horizontal_rule,
     horizontal_space, pick_list, progress_bar, row,
-    scrollable, slider, text, text_input, toggler,
-    vertical_rule, vertical_space, Text, Scrollable, Theme, Image
+    scrollable, text, text_input, toggler,
+    vertical_rule, vertical_space, Theme,
 };
 use iced::Alignment::Center;
-use iced::{Element, Length, Fill, Color, Size, Subscription, Renderer, Application, Font};
+use iced::{Element, Length, Fill, Color, Task};
+use iced::futures::SinkExt;
+use std::time::Duration;
 use rfd::FileDialog;
-use DiskScanner::scanner::{drive_usage, scan_directory, compute_folder_stats};
+use DiskScanner::scanner::{drive_usage, scan_tree, DirNode, DirTree, NodeId, ScanControl, ScanOptions, ScanProgress, ScanReport, SortOrder};
+use DiskScanner::scanner::utils::{human_readable_age, human_readable_delta, human_readable_mode, human_readable_size, human_readable_time};
 use std::default::Default;
-use DiskScanner::scanner::FileEntry;
+use DiskScanner::scanner::{read_ncdu, write_ncdu, write_scan, ExportFormat};
+use DiskScanner::scanner::{load_snapshot, save_snapshot, Snapshot, SNAPSHOT_EXTENSION};
+use DiskScanner::scanner::{diff_trees, Change, TreeDiff};
+use DiskScanner::scanner::{History, HistoryIndex};
+use DiskScanner::scanner::{confirm_duplicates, same_size_groups, DuplicateGroup, Duplicates};
+use DiskScanner::scanner::{link_duplicates, remove_empty_folder, retarget_symlink, LinkMode};
+use DiskScanner::scanner::{find_empty, EmptyEntries};
+use DiskScanner::scanner::{largest_entries, LargestEntries};
+use DiskScanner::scanner::{find_symlinks, inspect_symlink, LinkStatus, SymlinkEntry};
+use DiskScanner::scanner::{list_mounts, DriveUsage, FileEntry, FolderStats, InodeHotspot, MountEntry};
+
+mod charts;
+
+// Duplicate groups listed at once, the most reclaimable first
+const MAX_DUPLICATE_GROUPS: usize = 200;
+// Files and folders on the Largest Items page, each
+const LARGEST_ITEMS: usize = 50;
+
+use charts::{ChartCache, GrowthChart, Sunburst, Treemap};
 
 pub fn main() -> iced::Result {
     iced::application("Disk Scanner", Styling::update, Styling::view)
         .theme(Styling::theme)
-        .run()
+        .run_with(Styling::new)
+}
+
+// How the right side shows the items of the current folder
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum ItemView {
+    #[default]
+    List,
+    Treemap,
+    Sunburst,
+}
+
+impl ItemView {
+    const ALL: [ItemView; 3] = [ItemView::List, ItemView::Treemap, ItemView::Sunburst];
+}
+
+impl std::fmt::Display for ItemView {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ItemView::List => "List",
+            ItemView::Treemap => "Treemap",
+            ItemView::Sunburst => "Sunburst",
+        };
+        write!(f, "{}", name)
+    }
 }
 
 // Define the pages of your application
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 enum Page {
+    #[default]
     Home,
     Normal,
-}
-
-impl Default for Page {
-    fn default() -> Self {
-        Page::Home
-    }
+    InodeHotspots,
+    LargestItems,
+    UnreadablePaths,
+    Duplicates,
+    EmptyEntries,
+    Symlinks,
+    Scanning,
+    Settings,
 }
 
 #[derive(Debug, Clone)]
@@ -44,47 +92,298 @@ enum Message {
     Back,
     OpenFile(PathBuf),
     DeleteFile(PathBuf),
-    SelectFile(usize), // Index of selected item in the list
+    RefreshMounts,
+    ShowInodeHotspots,
+    OpenHotspot(PathBuf),
+    CloseHotspots,
+    ShowLargest,
+    OpenLargest(PathBuf),
+    CloseLargest,
+    ShowUnreadablePaths,
+    ExportPressed,
+    OpenNcduPressed,
+    OpenSnapshotPressed,
+    SaveSnapshotPressed,
+    CompareSnapshotPressed,
+    CloseDiff,
+    CloseUnreadablePaths,
+    ToggleDiskUsage(bool),
+    ShowDuplicates,
+    FindDuplicates,
+    CancelDuplicates,
+    CloseDuplicates,
+    DuplicatesProgress(u64, ScanProgress), // search id, like ScanProgress
+    DuplicatesFound(u64, Option<Duplicates>),
+    SetKeep(PathBuf, bool),
+    DeleteUnkeptDuplicates,
+    ConfirmDelete,
+    CancelDelete,
+    LinkUnkeptDuplicates(LinkMode),
+    ConfirmLink,
+    ShowEmpty,
+    CloseEmpty,
+    SetEmptySelected(PathBuf, bool),
+    SelectAllEmpty(bool),
+    RemoveSelectedEmpty,
+    ShowSymlinks,
+    CloseSymlinks,
+    SetSymlinkTarget(PathBuf, String),
+    RetargetSymlink(PathBuf),
+    RemoveSymlinks(Vec<PathBuf>),
+    SetItemView(ItemView),
+    ChartClicked(PathBuf),
+    HistoryLoaded(Result<HistoryIndex, String>),
+    HistoryWritten(Result<(), String>),
+    MountsLoaded(Result<Vec<MountEntry>, String>),
+    ScanProgress(u64, ScanProgress), // scan id, so late messages from a replaced scan are ignored
+    ScanFinished(u64, Option<DirTree>),
+    CancelScan,
+    ShowSettings,
+    CloseSettings,
+    SetMaxDepth(String),
+    SetThreads(String),
+    SetFollowSymlinks(bool),
+    SetOneFileSystem(bool),
+    SetIncludeHidden(bool),
+    SetSortOrder(SortOrder),
+    SetExcludePatterns(String),
+    SetIncludePatterns(String),
+    SetUseIgnoreFiles(bool),
 }
 
 #[derive(Default, Clone)]
 struct Styling {
     theme: Theme,
     selected_path: Option<PathBuf>,
-    files_name: Vec<FileEntry>, 
+    items: Vec<NodeId>, // children of the current folder in the scanned tree
     description: Vec<String>,
     total: Vec<String>,
     usage_percentage: f64, 
     path_name: String,
     current_page: Page, // Track the current page
-    Root_path: Option<PathBuf>,
-    files: Vec<FileEntry>,
+    root_path: Option<PathBuf>,
     selected_index: Option<usize>,
     last_click_time: Option<std::time::Instant>,
+    mounts: Vec<MountEntry>,
+    mounts_loading: bool, // the mount table is being read, Refresh waits for it
+    inode_hotspots: Vec<InodeHotspot>,
+    largest: LargestEntries,
+    folder_stats: Option<FolderStats>,
+    show_allocated: bool, // disk usage (`du`) instead of apparent size (`du --apparent-size`)
+    tree: Option<DirTree>,
+    scan_id: u64,
+    scan_control: Option<ScanControl>,
+    scan_progress: ScanProgress,
+    scan_path: Option<PathBuf>,
+    previous_page: Page, // where Cancel and the settings page go back to
+    scan_options: ScanOptions,
+    max_depth_input: String,
+    threads_input: String,
+    exclude_input: String,
+    include_input: String,
+    offline: bool, // the tree was loaded from a file, its paths may not exist on this machine
+    scanned_at: Option<SystemTime>,
+    from_snapshot: bool, // browsing a saved snapshot, the banner shows how old it is
+    diff: Option<TreeDiff>, // set in diff mode: an older snapshot compared against the current tree
+    history: Option<History>, // None when there is no data directory to keep it in
+    history_index: HistoryIndex,
+    item_view: ItemView,
+    chart_cache: ChartCache, // cleared whenever the tree, the folder shown, the size mode or the view changes
+    duplicates: Option<Duplicates>,
+    keep: HashSet<PathBuf>, // duplicate copies ticked to be kept, every other copy gets deleted
+    duplicate_id: u64,
+    duplicate_control: Option<ScanControl>,
+    duplicate_progress: ScanProgress,
+    pending_delete: Vec<PathBuf>, // waiting for the user to confirm, see view_confirm_delete
+    pending_link: Option<LinkMode>, // same for replacing the unticked duplicates with links
+    empty: Option<EmptyEntries>,
+    empty_selected: HashSet<PathBuf>, // empty folders and files ticked for removal
+    symlinks: Option<Vec<SymlinkEntry>>,
+    symlink_targets: HashMap<PathBuf, String>, // new targets typed in, by link
+    action_status: String, // outcome of the last bulk delete or link, shown on the page that asked for it
 }
 
 impl Styling {
-    fn update(&mut self, message: Message) {
+    fn new() -> (Self, Task<Message>) {
+        let mut app = Self::default();
+        let load_mounts = app.refresh_mounts();
+        app.history = History::default_location().map(|file| History::open(&file));
+        // The history file can be big, it is read (and compacted) off the UI thread
+        let load_history = match app.history.clone() {
+            Some(history) => blocking_task(
+                move || history.load_compacted().map_err(|e| format!("{}: {}", history.file().display(), e)),
+                Message::HistoryLoaded,
+            ),
+            None => Task::none(),
+        };
+        (app, Task::batch([load_mounts, load_history]))
+    }
+
+    // Every mount gets a statvfs call, a hung network share would block the UI, so they are read on a worker
+    fn refresh_mounts(&mut self) -> Task<Message> {
+        self.mounts_loading = true;
+        blocking_task(
+            || {
+                list_mounts().map_err(|e| e.to_string()).map(|mounts| {
+                    // Only real storage, skip proc, sysfs, cgroup and friends
+                    mounts
+                        .into_iter()
+                        .filter(|mount| mount.usage.as_ref().is_some_and(|usage| usage.total_space > 0))
+                        .collect()
+                })
+            },
+            Message::MountsLoaded,
+        )
+    }
+
+    fn update(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::FilePressed => {
                 let folder = FileDialog::new().pick_folder();
-                self.update(Message::FolderSelected(folder));
+                return self.update(Message::FolderSelected(folder));
             }
             Message::FolderSelected(Some(path)) => {
-                self.selected_path = Some(path.clone());
-                self.current_page = Page::Normal; // Move to Normal page directly
-                
-                // Update files in the directory
-                self.files_name = scan_directory(&path); // Scan directory
-                
-                // Get drive usage stats
-                let (descriptions, usage_percentage) = drive_usage(&path);
-                self.description = descriptions;
-                self.usage_percentage = usage_percentage; // Store the usage percentage for the progress bar
-                
-                self.path_name = path.to_string_lossy().to_string();
-                self.load_folder_contents(&path); // Load folder contents
-                self.Root_path = Some(path.clone());
+                // Only one scan at a time, a new folder replaces the running one
+                if let Some(control) = &self.scan_control {
+                    control.cancel();
+                }
+                let control = ScanControl::new();
+                self.scan_id += 1;
+                self.scan_control = Some(control.clone());
+                self.scan_progress = ScanProgress::default();
+                self.scan_path = Some(path.clone());
+                if self.current_page != Page::Scanning {
+                    self.previous_page = self.current_page.clone();
+                }
+                self.current_page = Page::Scanning;
+
+                return scan_task(self.scan_id, path, self.scan_options.clone(), control);
+            }
+
+            Message::HistoryLoaded(loaded) => match loaded {
+                Ok(mut index) => {
+                    // Scans that finished while the file was being read are newer than anything in it
+                    index.merge(std::mem::take(&mut self.history_index));
+                    self.history_index = index;
+                }
+                Err(e) => println!("Failed to read scan history {}", e),
+            },
+
+            Message::HistoryWritten(written) => {
+                if let Err(e) = written {
+                    println!("Failed to write scan history {}", e);
+                }
+            }
+
+            Message::MountsLoaded(loaded) => {
+                self.mounts_loading = false;
+                match loaded {
+                    Ok(mounts) => self.mounts = mounts,
+                    Err(e) => println!("Failed to read mount table: {}", e),
+                }
+            }
+
+            Message::ScanProgress(id, progress) => {
+                if id == self.scan_id && self.scan_control.is_some() {
+                    self.scan_progress = progress;
+                }
+            }
+
+            Message::ScanFinished(id, tree) => {
+                if id != self.scan_id || self.scan_control.is_none() {
+                    return Task::none();
+                }
+                self.scan_control = None;
+
+                match (tree, self.scan_path.take()) {
+                    (Some(tree), Some(path)) => {
+                        // Scan once, every navigation afterwards is served from the tree
+                        self.offline = false;
+                        let scanned_at = SystemTime::now();
+                        let write_history = self.record_history(&tree, scanned_at);
+                        self.show_tree(tree, &path, scanned_at, false);
+                        return write_history;
+                    }
+                    _ => {
+                        println!("Scan failed or was cancelled.");
+                        self.current_page = self.previous_page.clone();
+                    }
+                }
+            }
+
+            Message::ShowSettings => {
+                self.previous_page = self.current_page.clone();
+                self.current_page = Page::Settings;
+            }
+
+            Message::CloseSettings => {
+                self.current_page = self.previous_page.clone();
+            }
+
+            Message::SetMaxDepth(input) => {
+                // Empty means unlimited, anything that isn't a number is ignored
+                if input.trim().is_empty() {
+                    self.scan_options.max_depth = None;
+                    self.max_depth_input = input;
+                } else if let Ok(depth) = input.trim().parse() {
+                    self.scan_options.max_depth = Some(depth);
+                    self.max_depth_input = input;
+                }
+            }
+
+            Message::SetThreads(input) => {
+                if input.trim().is_empty() {
+                    self.scan_options.threads = 0;
+                    self.threads_input = input;
+                } else if let Ok(threads) = input.trim().parse() {
+                    self.scan_options.threads = threads;
+                    self.threads_input = input;
+                }
+            }
+
+            Message::SetFollowSymlinks(follow_symlinks) => {
+                self.scan_options.follow_symlinks = follow_symlinks;
+            }
+
+            Message::SetOneFileSystem(one_file_system) => {
+                self.scan_options.one_file_system = one_file_system;
+            }
+
+            Message::SetIncludeHidden(include_hidden) => {
+                self.scan_options.include_hidden = include_hidden;
+            }
+
+            Message::SetExcludePatterns(input) => {
+                self.scan_options.exclude = split_patterns(&input);
+                self.exclude_input = input;
+            }
+
+            Message::SetIncludePatterns(input) => {
+                self.scan_options.include = split_patterns(&input);
+                self.include_input = input;
+            }
+
+            Message::SetUseIgnoreFiles(use_ignore_files) => {
+                self.scan_options.use_ignore_files = use_ignore_files;
+            }
+
+            Message::SetSortOrder(sort_order) => {
+                self.scan_options.sort_order = sort_order;
+                // Sorting doesn't need a rescan, reorder the tree we already have
+                if let Some(tree) = &mut self.tree {
+                    tree.sort_children(sort_order);
+                }
+                if let Some(current_path) = self.selected_path.clone() {
+                    self.load_folder_contents(&current_path);
+                }
+            }
+
+            Message::CancelScan => {
+                if let Some(control) = self.scan_control.take() {
+                    control.cancel();
+                }
+                self.scan_path = None;
+                self.current_page = self.previous_page.clone();
             }
 
             Message::FolderSelected(None) => {
@@ -107,46 +406,33 @@ impl Styling {
                 }
             },
 
-            Message::SelectFile(index) => {
-                self.selected_index = Some(index);  // Update selected index
-            },
-
             Message::DeleteFile(path) => {
 
                 if let Some(selected_path) = &self.selected_path {
                     let full_path = selected_path.join(path);
             
-                    println!("Attempting to delete: {}", full_path.display());
-            
-                    // Attempt to delete the file
-                    if let Err(e) = std::fs::remove_file(&full_path) {
-                        println!("Failed to delete file: {}", e);
-                    } else {
-                        //remove from the list
-                        self.files.retain(|file| file.path != full_path);
+                    let current_path = selected_path.clone();
+                    if self.delete_file(&full_path) {
+                        self.load_folder_contents(&current_path);
                     }
                 } else {
                     println!("Selected path is not set.");
                 }
             },
             Message::SelectItem(index) => {
-                if let Some(selected_item) = self.files_name.get(index) {
+                let selected_item = match (&self.tree, self.items.get(index)) {
+                    (Some(tree), Some(&id)) => Some(tree.node(id).entry.clone()),
+                    _ => None,
+                };
+                if let Some(selected_item) = selected_item {
                     if let Some(last_click) = self.last_click_time {
                         // Double-click
                         if last_click.elapsed().as_secs_f32() < 0.3 {
                             //detected
                             if selected_item.is_folder {
-                                let new_path = self.selected_path.as_ref().map(|path| path.join(&selected_item.name));
-                                if let Some(path) = new_path {
-                                    self.load_folder_contents(&path);
-                                    self.path_name = path.to_string_lossy().to_string();
-                                }
+                                self.load_folder_contents(&selected_item.path);
                             } else {
-                                self.description = vec![
-                                    format!("Name : {} ", selected_item.name),
-                                    format!("Size: {} Mb", selected_item.size),
-                                    format!("Last Modified: {}", selected_item.modified),
-                                ];
+                                self.description = file_entry_lines(&selected_item);
                             }
                         } else {
                             // Single-click
@@ -159,9 +445,401 @@ impl Styling {
                 }
             }
 
+            Message::RefreshMounts => {
+                return self.refresh_mounts();
+            }
+
+            Message::ShowInodeHotspots => {
+                if let Some(tree) = &self.tree {
+                    self.inode_hotspots = tree.inode_hotspots(tree.root(), 50);
+                    self.current_page = Page::InodeHotspots;
+                }
+            }
+
+            Message::OpenHotspot(path) => {
+                self.load_folder_contents(&path);
+                self.current_page = Page::Normal;
+            }
+
+            Message::CloseHotspots => {
+                self.current_page = Page::Normal;
+            }
+
+            Message::ShowLargest => {
+                if let Some(tree) = &self.tree {
+                    self.largest = largest_entries(tree, tree.root(), LARGEST_ITEMS, self.show_allocated);
+                    self.current_page = Page::LargestItems;
+                }
+            }
+
+            Message::OpenLargest(path) => {
+                // Open the folder holding it, with it selected and its details on the left
+                let Some((id, entry)) = self.tree.as_ref().and_then(|tree| {
+                    let id = tree.find(&path)?;
+                    Some((id, tree.node(id).entry.clone()))
+                }) else {
+                    return Task::none();
+                };
+                if let Some(parent) = path.parent() {
+                    self.load_folder_contents(parent);
+                }
+                self.selected_index = self.items.iter().position(|&item| item == id);
+                self.description = match &self.tree {
+                    Some(tree) if entry.is_folder => self.folder_stats_lines(&tree.folder_stats(id)),
+                    _ => file_entry_lines(&entry),
+                };
+                self.current_page = Page::Normal;
+            }
+
+            Message::CloseLargest => {
+                self.current_page = Page::Normal;
+            }
+
+            Message::ExportPressed => {
+                self.export_current_folder();
+            }
+
+            Message::OpenNcduPressed => {
+                let Some(file) = FileDialog::new().add_filter("ncdu export", &["json", "ncdu"]).pick_file() else {
+                    return Task::none();
+                };
+                match std::fs::File::open(&file).and_then(|file| read_ncdu(std::io::BufReader::new(file))) {
+                    Ok(tree) => {
+                        let root = tree.path(tree.root()).to_path_buf();
+                        // ncdu's timestamp isn't kept, the file's own age is close enough
+                        let scanned_at = std::fs::metadata(&file).and_then(|meta| meta.modified()).unwrap_or_else(|_| SystemTime::now());
+                        self.offline = true;
+                        self.show_tree(tree, &root, scanned_at, false);
+                    }
+                    Err(e) => println!("Failed to open ncdu export {}: {}", file.display(), e),
+                }
+            }
+
+            Message::OpenSnapshotPressed => {
+                let Some(file) = FileDialog::new().add_filter("DiskScanner snapshot", &[SNAPSHOT_EXTENSION]).pick_file() else {
+                    return Task::none();
+                };
+                match load_snapshot(&file) {
+                    Ok(snapshot) => {
+                        self.offline = false;
+                        self.show_tree(snapshot.tree, &snapshot.root_path, snapshot.scanned_at, true);
+                    }
+                    Err(e) => println!("Failed to open snapshot {}: {}", file.display(), e),
+                }
+            }
+
+            Message::SaveSnapshotPressed => {
+                self.save_snapshot();
+            }
+
+            Message::CompareSnapshotPressed => {
+                let Some(file) = FileDialog::new().add_filter("DiskScanner snapshot", &[SNAPSHOT_EXTENSION]).pick_file() else {
+                    return Task::none();
+                };
+                match (load_snapshot(&file), &self.tree) {
+                    (Ok(old), Some(tree)) => {
+                        let mut diff = diff_trees(&old.tree, tree);
+                        diff.old_scanned_at = Some(old.scanned_at);
+                        diff.new_scanned_at = self.scanned_at;
+                        self.diff = Some(diff);
+                        if let Some(current_path) = self.selected_path.clone() {
+                            self.load_folder_contents(&current_path);
+                        }
+                    }
+                    (Err(e), _) => println!("Failed to open snapshot {}: {}", file.display(), e),
+                    _ => {}
+                }
+            }
+
+            Message::CloseDiff => {
+                self.diff = None;
+                if let Some(current_path) = self.selected_path.clone() {
+                    self.load_folder_contents(&current_path);
+                }
+            }
+
+            Message::ShowUnreadablePaths => {
+                if self.tree.is_some() {
+                    self.current_page = Page::UnreadablePaths;
+                }
+            }
+
+            Message::CloseUnreadablePaths => {
+                self.current_page = Page::Normal;
+            }
+
+            Message::ToggleDiskUsage(show_allocated) => {
+                self.show_allocated = show_allocated;
+                self.chart_cache.clear();
+                if let Some(stats) = &self.folder_stats {
+                    self.description = self.folder_stats_lines(stats);
+                }
+                if let (Some(diff), Some(current_path)) = (&self.diff, &self.selected_path) {
+                    self.description.extend(diff_lines(diff, current_path, show_allocated));
+                }
+            }
+
+            Message::ShowDuplicates => {
+                if self.tree.is_some() && !self.offline {
+                    self.current_page = Page::Duplicates;
+                    if self.duplicates.is_none() && self.duplicate_control.is_none() {
+                        return self.update(Message::FindDuplicates);
+                    }
+                }
+            }
+
+            Message::FindDuplicates => {
+                let Some(tree) = &self.tree else { return Task::none() };
+                if let Some(control) = &self.duplicate_control {
+                    control.cancel();
+                }
+                // Grouping by size is quick, only the hashing runs in the background
+                let groups = same_size_groups(tree, tree.root(), 1);
+                let control = ScanControl::new();
+                self.duplicate_id += 1;
+                self.duplicate_control = Some(control.clone());
+                self.duplicate_progress = ScanProgress::default();
+                self.duplicates = None;
+                self.pending_delete.clear();
+                self.pending_link = None;
+                self.action_status.clear();
+                return duplicates_task(self.duplicate_id, groups, control);
+            }
+
+            Message::DuplicatesProgress(id, progress) => {
+                if id == self.duplicate_id && self.duplicate_control.is_some() {
+                    self.duplicate_progress = progress;
+                }
+            }
+
+            Message::DuplicatesFound(id, duplicates) => {
+                if id != self.duplicate_id || self.duplicate_control.take().is_none() {
+                    return Task::none();
+                }
+                // Keep the first copy of every group unless told otherwise
+                self.keep = duplicates
+                    .iter()
+                    .flat_map(|duplicates| duplicates.groups.iter())
+                    .filter_map(|group| group.files.first().cloned())
+                    .collect();
+                self.duplicates = duplicates;
+            }
+
+            Message::CancelDuplicates => {
+                if let Some(control) = self.duplicate_control.take() {
+                    control.cancel();
+                }
+                self.current_page = Page::Normal;
+            }
+
+            Message::CloseDuplicates => {
+                self.pending_delete.clear();
+                self.pending_link = None;
+                self.current_page = Page::Normal;
+            }
+
+            Message::SetKeep(path, keep) => {
+                if keep {
+                    self.keep.insert(path);
+                } else {
+                    self.keep.remove(&path);
+                }
+            }
+
+            Message::DeleteUnkeptDuplicates => {
+                self.pending_link = None;
+                self.pending_delete = self.unkept_duplicates().0;
+            }
+
+            Message::ConfirmDelete => {
+                let paths = std::mem::take(&mut self.pending_delete);
+                let requested = paths.len();
+                let mut deleted = HashSet::new();
+                for path in paths {
+                    if self.delete_file(&path) {
+                        deleted.insert(path);
+                    }
+                }
+                self.action_status = format!("Deleted {} of {} items", deleted.len(), requested);
+                // Drop what is gone from the duplicate groups, and groups down to a single copy
+                if let Some(duplicates) = &mut self.duplicates {
+                    for group in &mut duplicates.groups {
+                        group.files.retain(|file| !deleted.contains(file));
+                    }
+                    duplicates.groups.retain(|group| group.files.len() > 1);
+                }
+                if let Some(empty) = &mut self.empty {
+                    empty.folders.retain(|folder| !deleted.contains(folder));
+                    empty.files.retain(|file| !deleted.contains(file));
+                }
+                if let Some(symlinks) = &mut self.symlinks {
+                    symlinks.retain(|link| !deleted.contains(&link.path));
+                }
+                if let Some(current_path) = self.selected_path.clone() {
+                    self.load_folder_contents(&current_path);
+                }
+            }
+
+            Message::CancelDelete => {
+                self.pending_delete.clear();
+                self.pending_link = None;
+            }
+
+            Message::LinkUnkeptDuplicates(mode) => {
+                self.pending_delete.clear();
+                self.pending_link = Some(mode);
+            }
+
+            Message::ConfirmLink => {
+                let Some(mode) = self.pending_link.take() else { return Task::none() };
+                // Every unticked copy becomes a link to the first ticked one of its group
+                let jobs: Vec<(PathBuf, Vec<PathBuf>)> = self.shown_duplicate_groups()
+                    .iter()
+                    .filter_map(|group| {
+                        let keep = group.files.iter().find(|file| self.keep.contains(*file))?;
+                        let rest = group.files.iter().filter(|file| !self.keep.contains(*file)).cloned().collect();
+                        Some((keep.clone(), rest))
+                    })
+                    .collect();
+
+                let mut linked = HashSet::new();
+                let mut reclaimed = 0;
+                let mut failed = 0;
+                for (keep, rest) in jobs {
+                    let report = link_duplicates(&keep, &rest, mode);
+                    for (path, error) in &report.failed {
+                        println!("Failed to link {} to {}: {}", path.display(), keep.display(), error);
+                    }
+                    reclaimed += report.reclaimed;
+                    failed += report.failed.len();
+                    linked.extend(report.replaced);
+                }
+                self.action_status = format!(
+                    "Linked {} files, {} reclaimed{} (rescan to update the totals)",
+                    linked.len(),
+                    human_readable_size(reclaimed),
+                    if failed > 0 { format!(", {} failed", failed) } else { String::new() },
+                );
+                // Linked copies no longer take space of their own
+                if let Some(duplicates) = &mut self.duplicates {
+                    for group in &mut duplicates.groups {
+                        group.files.retain(|file| !linked.contains(file));
+                    }
+                    duplicates.groups.retain(|group| group.files.len() > 1);
+                }
+            }
+
+            Message::ShowEmpty => {
+                if let (Some(tree), false) = (&self.tree, self.offline) {
+                    // One pass over the tree, quick enough to redo every time the page opens
+                    // Nothing is ticked to start with, removing has to be asked for item by item or with Select All
+                    self.empty = Some(find_empty(tree, tree.root()));
+                    self.empty_selected.clear();
+                    self.pending_delete.clear();
+                    self.action_status.clear();
+                    self.current_page = Page::EmptyEntries;
+                }
+            }
+
+            Message::CloseEmpty => {
+                self.pending_delete.clear();
+                self.current_page = Page::Normal;
+            }
+
+            Message::SetEmptySelected(path, selected) => {
+                if selected {
+                    self.empty_selected.insert(path);
+                } else {
+                    self.empty_selected.remove(&path);
+                }
+            }
+
+            Message::SelectAllEmpty(selected) => {
+                self.empty_selected = match (&self.empty, selected) {
+                    (Some(empty), true) => empty.folders.iter().chain(&empty.files).cloned().collect(),
+                    _ => HashSet::new(),
+                };
+            }
+
+            Message::RemoveSelectedEmpty => {
+                if let Some(empty) = &self.empty {
+                    self.pending_delete = empty.folders
+                        .iter()
+                        .chain(&empty.files)
+                        .filter(|path| self.empty_selected.contains(*path))
+                        .cloned()
+                        .collect();
+                }
+            }
+
+            Message::ShowSymlinks => {
+                if let (Some(tree), false) = (&self.tree, self.offline) {
+                    // Every link is checked again, whatever it pointed at during the scan may have changed since
+                    self.symlinks = Some(find_symlinks(tree, tree.root()));
+                    self.symlink_targets.clear();
+                    self.pending_delete.clear();
+                    self.action_status.clear();
+                    self.current_page = Page::Symlinks;
+                }
+            }
+
+            Message::CloseSymlinks => {
+                self.pending_delete.clear();
+                self.current_page = Page::Normal;
+            }
+
+            Message::SetSymlinkTarget(path, target) => {
+                self.symlink_targets.insert(path, target);
+            }
+
+            Message::RetargetSymlink(path) => {
+                let Some(target) = self.symlink_targets.get(&path).map(|target| PathBuf::from(target.trim())) else {
+                    return Task::none();
+                };
+                match retarget_symlink(&path, &target) {
+                    Ok(()) => {
+                        self.symlink_targets.remove(&path);
+                        let checked = inspect_symlink(&path);
+                        if let (Some(symlinks), Ok(checked)) = (&mut self.symlinks, checked) {
+                            self.action_status = format!("{} now points to {} ({})", path.display(), target.display(), checked.status);
+                            if let Some(link) = symlinks.iter_mut().find(|link| link.path == path) {
+                                *link = checked;
+                            }
+                        }
+                    }
+                    Err(e) => self.action_status = format!("Failed to retarget {}: {}", path.display(), e),
+                }
+            }
+
+            Message::RemoveSymlinks(paths) => {
+                self.pending_delete = paths;
+            }
+
+            Message::SetItemView(item_view) => {
+                self.item_view = item_view;
+                self.chart_cache.clear();
+            }
+
+            Message::ChartClicked(path) => {
+                // Folders open, files open their folder and show their details
+                let entry = self.tree.as_ref().and_then(|tree| Some(tree.node(tree.find(&path)?).entry.clone()));
+                match entry {
+                    Some(entry) if entry.is_folder => self.load_folder_contents(&path),
+                    Some(entry) => {
+                        if let Some(parent) = path.parent() {
+                            if self.selected_path.as_deref() != Some(parent) {
+                                self.load_folder_contents(parent);
+                            }
+                        }
+                        self.description = file_entry_lines(&entry);
+                    }
+                    None => {}
+                }
+            }
+
             Message::Back => {
                 if let Some(current_path) = &self.selected_path {
-                    if let Some(root_path) = &self.Root_path {
+                    if let Some(root_path) = &self.root_path {
                         if current_path != root_path {
                             
                             if let Some(parent) = current_path.parent() {
@@ -177,17 +855,121 @@ impl Styling {
                 }
             }
         }
+
+        Task::none()
     }
 
-    fn view(&self) -> Element<Message> {
+    fn view(&self) -> Element<'_, Message> {
         match self.current_page {
             Page::Home => self.view_home(),     
             Page::Normal => self.view_normal(),
+            Page::InodeHotspots => self.view_inode_hotspots(),
+            Page::LargestItems => self.view_largest(),
+            Page::Duplicates => self.view_duplicates(),
+            Page::EmptyEntries => self.view_empty(),
+            Page::Symlinks => self.view_symlinks(),
+            Page::UnreadablePaths => self.view_unreadable_paths(),
+            Page::Scanning => self.view_scanning(),
+            Page::Settings => self.view_settings(),
         }
     }
 
+    // Scan options, applied to the next scan (sort order applies right away)
+    fn view_settings(&self) -> Element<'_, Message> {
+        let options = &self.scan_options;
+
+        column![
+            row![
+                button("Go Back")
+                    .on_press(Message::CloseSettings)
+                    .padding(20)
+                    .style(grey_button_style),
+                horizontal_space().width(20),
+                text("Scan Settings").size(30),
+            ]
+            .align_y(Center),
+            horizontal_rule(20),
+            row![
+                text("Max depth").width(200),
+                text_input("unlimited", &self.max_depth_input)
+                    .on_input(Message::SetMaxDepth)
+                    .width(150),
+            ]
+            .align_y(Center),
+            row![
+                text("Threads").width(200),
+                text_input("all CPUs", &self.threads_input)
+                    .on_input(Message::SetThreads)
+                    .width(150),
+                horizontal_space().width(10),
+                text("1 uses the sequential walker").size(14),
+            ]
+            .align_y(Center),
+            row![
+                text("Sort order").width(200),
+                pick_list(&SortOrder::ALL[..], Some(options.sort_order), Message::SetSortOrder),
+            ]
+            .align_y(Center),
+            checkbox("Follow symlinks", options.follow_symlinks)
+                .on_toggle(Message::SetFollowSymlinks),
+            checkbox("Stay on one filesystem (du -x)", options.one_file_system)
+                .on_toggle(Message::SetOneFileSystem),
+            checkbox("Include hidden files", options.include_hidden)
+                .on_toggle(Message::SetIncludeHidden),
+            row![
+                text("Exclude").width(200),
+                text_input("node_modules, *.log, /build", &self.exclude_input)
+                    .on_input(Message::SetExcludePatterns)
+                    .width(400),
+            ]
+            .align_y(Center),
+            row![
+                text("Only include files").width(200),
+                text_input("everything", &self.include_input)
+                    .on_input(Message::SetIncludePatterns)
+                    .width(400),
+                horizontal_space().width(10),
+                text("comma separated, .gitignore syntax").size(14),
+            ]
+            .align_y(Center),
+            checkbox("Honor .gitignore and .ignore files", options.use_ignore_files)
+                .on_toggle(Message::SetUseIgnoreFiles),
+        ]
+        .spacing(15)
+        .padding(20)
+        .into()
+    }
+
+    // Live progress of the background scan
+    fn view_scanning(&self) -> Element<'_, Message> {
+        let progress = &self.scan_progress;
+        let scan_path = self.scan_path
+            .as_ref()
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        column![
+            text(format!("Scanning {}", scan_path)).size(30),
+            horizontal_rule(20),
+            text(format!("Files seen: {}", progress.files_seen)).size(20),
+            text(format!("Bytes counted: {}", human_readable_size(progress.bytes_counted))).size(20),
+            text(format!("Elapsed: {:.1} s", progress.elapsed.as_secs_f64())).size(20),
+            text(format!("Current folder: {}", progress.current_path.display())).size(16),
+            vertical_space().height(20),
+            button("Cancel")
+                .on_press(Message::CancelScan)
+                .padding(20)
+                .style(grey_button_style),
+        ]
+        .spacing(10)
+        .padding(40)
+        .width(Length::Fill)
+        .align_x(Center)
+        .into()
+    }
+
     // Home page view
-    fn view_home(&self) -> Element<Message> {
+    fn view_home(&self) -> Element<'_, Message> {
 
         //GOOFY IMAGE
         let image_path = std::path::Path::new("assets/image.jpg");
@@ -219,6 +1001,16 @@ impl Styling {
                 }
             });
 
+        let drives: Element<'_, Message> = if self.mounts.is_empty() && self.mounts_loading {
+            text("Reading drives...").into()
+        } else {
+            scrollable(
+                column(self.mounts.iter().map(|mount| mount_card(mount))).spacing(10),
+            )
+            .height(Length::Fill)
+            .into()
+        };
+
         row![
             column![
             image(img_handle)
@@ -226,9 +1018,39 @@ impl Styling {
                 .height(300),
             horizontal_rule(20),
             file_button,
+            vertical_space().height(10),
+            button("Open Snapshot")
+                .on_press(Message::OpenSnapshotPressed)
+                .padding(10)
+                .style(grey_button_style),
+            vertical_space().height(10),
+            button("Open ncdu Export")
+                .on_press(Message::OpenNcduPressed)
+                .padding(10)
+                .style(grey_button_style),
+            vertical_space().height(10),
+            button("Settings")
+                .on_press(Message::ShowSettings)
+                .padding(10)
+                .style(grey_button_style),
+            ]
+            .width(Length::Fill)
+            .align_x(Center),
+            vertical_rule(30),
+            column![
+                row![
+                    text("Drives").size(30),
+                    horizontal_space(),
+                    button(text("Refresh"))
+                        .on_press_maybe((!self.mounts_loading).then_some(Message::RefreshMounts))
+                        .padding(10)
+                        .style(grey_button_style),
+                ]
+                .align_y(Center),
+                drives,
             ]
+            .spacing(10)
             .width(Length::Fill)
-            .align_x(Center)
         ]
         .align_y(iced::alignment::Vertical::Center)
         .height(Fill)
@@ -238,7 +1060,25 @@ impl Styling {
     }
 
     // Normal page view
-    fn view_normal(&self) -> Element<Message> {
+    fn view_normal(&self) -> Element<'_, Message> {
+        let error_count = self.tree.as_ref().map_or(0, |tree| tree.report().len());
+
+        // Saved snapshots can be arbitrarily stale, say so up front
+        let snapshot_banner: Element<'_, Message> = match self.scanned_at.filter(|_| self.from_snapshot) {
+            Some(scanned_at) => container(
+                text(format!(
+                    "Snapshot taken {} ({}), sizes may have changed since",
+                    human_readable_time(scanned_at),
+                    human_readable_age(SystemTime::now().duration_since(scanned_at).unwrap_or_default()),
+                ))
+                .size(16),
+            )
+            .padding(10)
+            .width(Length::Fill)
+            .style(container::rounded_box)
+            .into(),
+            None => text("").into(),
+        };
 
         let usage_bar = progress_bar(0.0..=100.0, self.usage_percentage as f32)
             .width(300)
@@ -268,7 +1108,7 @@ impl Styling {
             });
 
         let back_button = if let Some(current_path) = &self.selected_path {
-            if let Some(root_path) = &self.Root_path {
+            if let Some(root_path) = &self.root_path {
                 if current_path == root_path {
                     None
                 } else {
@@ -307,7 +1147,9 @@ impl Styling {
         
 
         let list = scrollable(
-            column(self.files_name.iter().enumerate().map(|(index, file_entry)| {
+            column(self.items.iter().enumerate().filter_map(|(index, &id)| {
+                let node = self.tree.as_ref()?.node(id);
+                let file_entry = &node.entry;
                 let is_selected = self.selected_index == Some(index);
                 let folder_path = std::path::Path::new("assets/folder.png");
                 let file_path = std::path::Path::new("assets/text.png");
@@ -322,7 +1164,7 @@ impl Styling {
                     file_handle
                 };
 
-                row![
+                Some(row![
                     button(
                         row![
                         image(icon_handle)
@@ -354,14 +1196,12 @@ impl Styling {
                         vertical_space()
                         .height(10),
 
-                    // Only show file details if it's not a folder
-                    if !file_entry.is_folder {
-                        Some(text(format!("{} ", file_entry.size)))
-                    } else {
-                        None
-                    }.map_or(text(""), |text_element| text_element),
+                    // Folders show the recursive size of everything under them
+                    text(format!("{} ", human_readable_size(self.node_size(node)))),
+                    self.delta_text(&file_entry.path),
 
-                    if is_selected {
+                    // Imported trees describe another machine, there is nothing here to open or delete
+                    if is_selected && !self.offline {
                         row![
                             button(text("Open"))
                                 .on_press(Message::OpenFile(PathBuf::from(file_entry.name.clone())))
@@ -411,26 +1251,131 @@ impl Styling {
                                     }
                                 ]
                                 .spacing(10)
-                                .into()
+                                .into())
 
-                            }))
+                            }).chain(self.removed_rows()))
                             .spacing(0)
                         )
                         
                         .height(Length::Fill);  
 
-
-        let desc = scrollable(
+        // The same items as a chart, a few levels deep, sized by what the disk usage toggler says
+        let current = self.tree.as_ref().zip(self.selected_path.as_deref()).and_then(|(tree, path)| Some((tree, tree.find(path)?)));
+        let list: Element<'_, Message> = match (current, self.item_view) {
+            (Some((tree, root)), ItemView::Treemap) => canvas(Treemap {
+                tree,
+                root,
+                allocated: self.show_allocated,
+                on_click: Message::ChartClicked,
+                cache: &self.chart_cache,
+            })
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into(),
+            (Some((tree, root)), ItemView::Sunburst) => canvas(Sunburst {
+                tree,
+                root,
+                allocated: self.show_allocated,
+                on_click: Message::ChartClicked,
+                on_center: (self.selected_path != self.root_path).then_some(Message::Back),
+                cache: &self.chart_cache,
+            })
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into(),
+            _ => list.into(),
+        };
+
+
+        let desc = scrollable(
             column(self.description.iter().map(|desc| text(desc).size(16).into())).spacing(10),
         )
         .height(Length::Fill);
 
+        // Size of the current folder across every scan of it so far
+        let history = self.selected_path.as_deref().map_or(&[][..], |path| self.history_index.points(path));
+        let growth: Element<'_, Message> = match history.len() {
+            0 => text("").into(),
+            1 => text("Size history: scan this folder again to see how it grows").size(14).into(),
+            _ => column![
+                text(format!("Size history ({} scans)", history.len())).size(16),
+                canvas(GrowthChart { points: history.to_vec(), allocated: self.show_allocated })
+                    .width(Length::Fill)
+                    .height(200),
+            ]
+            .spacing(5)
+            .into(),
+        };
+        let desc = column![desc, growth].spacing(10).width(Length::Fill);
+
         column![
             row![
                 file_button,
                 horizontal_space().width(10),
-                back_button.map_or(iced::widget::text("").into(), |button| Element::from(button)),
+                back_button.map_or(iced::widget::text("").into(), Element::from),
+                horizontal_space(),
+                toggler(self.show_allocated)
+                    .label("Disk usage")
+                    .on_toggle(Message::ToggleDiskUsage)
+                    .width(Length::Shrink),
+                horizontal_space().width(20),
+                pick_list(&ItemView::ALL[..], Some(self.item_view), Message::SetItemView),
+                horizontal_space().width(20),
+                button("Inode Hotspots")
+                    .on_press(Message::ShowInodeHotspots)
+                    .padding(20)
+                    .style(grey_button_style),
+                horizontal_space().width(10),
+                button("Largest Items")
+                    .on_press(Message::ShowLargest)
+                    .padding(20)
+                    .style(grey_button_style),
+                horizontal_space().width(10),
+                // Hashing needs the files themselves, an imported tree only describes them
+                button("Duplicates")
+                    .on_press_maybe((!self.offline).then_some(Message::ShowDuplicates))
+                    .padding(20)
+                    .style(grey_button_style),
+                horizontal_space().width(10),
+                button("Empty Items")
+                    .on_press_maybe((!self.offline).then_some(Message::ShowEmpty))
+                    .padding(20)
+                    .style(grey_button_style),
+                horizontal_space().width(10),
+                button("Symlinks")
+                    .on_press_maybe((!self.offline).then_some(Message::ShowSymlinks))
+                    .padding(20)
+                    .style(grey_button_style),
+                horizontal_space().width(10),
+                button("Export")
+                    .on_press(Message::ExportPressed)
+                    .padding(20)
+                    .style(grey_button_style),
+                horizontal_space().width(10),
+                button("Save Snapshot")
+                    .on_press(Message::SaveSnapshotPressed)
+                    .padding(20)
+                    .style(grey_button_style),
+                horizontal_space().width(10),
+                if self.diff.is_some() {
+                    button("Exit Diff").on_press(Message::CloseDiff)
+                } else {
+                    button("Compare").on_press(Message::CompareSnapshotPressed)
+                }
+                .padding(20)
+                .style(grey_button_style),
+                horizontal_space().width(10),
+                button(text(format!("Unreadable Paths ({})", error_count)))
+                    .on_press(Message::ShowUnreadablePaths)
+                    .padding(20)
+                    .style(grey_button_style),
+                horizontal_space().width(10),
+                button("Settings")
+                    .on_press(Message::ShowSettings)
+                    .padding(20)
+                    .style(grey_button_style),
             ],
+            snapshot_banner,
             row![
                 text("Total Size")
                     .size(30)
@@ -476,38 +1421,911 @@ impl Styling {
         .into()
     }
 
+    // Directories with the most entries under the scanned root
+    fn view_inode_hotspots(&self) -> Element<'_, Message> {
+        let root_name = self.root_path
+            .as_ref()
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let list = scrollable(
+            column(self.inode_hotspots.iter().map(|hotspot| {
+                button(
+                    row![
+                        text(format!("{} entries", hotspot.entry_count))
+                            .width(150),
+                        text(hotspot.path.to_string_lossy().to_string()),
+                    ]
+                )
+                .on_press(Message::OpenHotspot(hotspot.path.clone()))
+                .padding(10)
+                .width(Length::Fill)
+                .style(grey_button_style)
+                .into()
+            }))
+            .spacing(5)
+        )
+        .height(Length::Fill);
+
+        column![
+            row![
+                button("Go Back")
+                    .on_press(Message::CloseHotspots)
+                    .padding(20)
+                    .style(grey_button_style),
+                horizontal_space().width(20),
+                text("Inode Hotspots").size(30),
+            ]
+            .align_y(Center),
+            text(format!("File Path : {}", root_name)).size(20),
+            list,
+        ]
+        .spacing(10)
+        .padding(20)
+        .into()
+    }
+
+    // The largest files and folders anywhere under the scanned root, clicking one opens the folder holding it
+    fn view_largest(&self) -> Element<'_, Message> {
+        let Some(tree) = &self.tree else {
+            return text("Nothing scanned yet").into();
+        };
+
+        let section = |title: &str, ids: &[NodeId]| -> Element<'_, Message> {
+            let rows = ids.iter().map(|&id| {
+                let path = tree.path(id).to_path_buf();
+                button(
+                    row![
+                        text(human_readable_size(self.node_size(tree.node(id)))).width(100),
+                        text(path.to_string_lossy().to_string()),
+                    ]
+                )
+                .on_press(Message::OpenLargest(path))
+                .padding(10)
+                .width(Length::Fill)
+                .style(grey_button_style)
+                .into()
+            });
+            column![
+                text(title.to_string()).size(20),
+                scrollable(column(rows).spacing(5)).height(Length::Fill),
+            ]
+            .spacing(10)
+            .width(Length::FillPortion(1))
+            .into()
+        };
+
+        column![
+            row![
+                button("Go Back")
+                    .on_press(Message::CloseLargest)
+                    .padding(20)
+                    .style(grey_button_style),
+                horizontal_space().width(20),
+                text("Largest Items").size(30),
+            ]
+            .align_y(Center),
+            text(format!(
+                "The {} largest files and folders under {}, by {}",
+                LARGEST_ITEMS,
+                tree.path(tree.root()).display(),
+                if self.show_allocated { "disk usage" } else { "apparent size" },
+            ))
+            .size(20),
+            row![
+                section("Files", &self.largest.files),
+                vertical_rule(20),
+                section("Folders", &self.largest.folders),
+            ]
+            .height(Length::Fill),
+        ]
+        .spacing(10)
+        .padding(20)
+        .into()
+    }
+
+    // Everything the last scan couldn't read, so wrong-looking totals can be explained
+    fn view_unreadable_paths(&self) -> Element<'_, Message> {
+        let empty = ScanReport::default();
+        let report = self.tree.as_ref().map_or(&empty, |tree| tree.report());
+
+        let counts = row(report.counts().into_iter().map(|(kind, count)| {
+            text(format!("{}: {}", kind, count)).size(20).into()
+        }))
+        .spacing(30);
+
+        let list = scrollable(
+            column(report.errors.iter().map(|error| {
+                row![
+                    text(error.kind.to_string()).width(200),
+                    text(error.path.to_string_lossy().to_string()),
+                ]
+                .padding(5)
+                .into()
+            }))
+            .spacing(5)
+        )
+        .height(Length::Fill);
+
+        column![
+            row![
+                button("Go Back")
+                    .on_press(Message::CloseUnreadablePaths)
+                    .padding(20)
+                    .style(grey_button_style),
+                horizontal_space().width(20),
+                text("Unreadable Paths").size(30),
+            ]
+            .align_y(Center),
+            text(format!("{} paths could not be read, their contents are missing from the totals", report.len())).size(20),
+            counts,
+            horizontal_rule(20),
+            list,
+        ]
+        .spacing(10)
+        .padding(20)
+        .into()
+    }
+
+    // Groups of identical files, every copy but the ticked ones can be deleted in one go
+    fn view_duplicates(&self) -> Element<'_, Message> {
+        let header = row![
+            button("Go Back")
+                .on_press(Message::CloseDuplicates)
+                .padding(20)
+                .style(grey_button_style),
+            horizontal_space().width(20),
+            text("Duplicates").size(30),
+        ]
+        .align_y(Center);
+
+        let Some(duplicates) = &self.duplicates else {
+            let progress = &self.duplicate_progress;
+            return column![
+                header,
+                horizontal_rule(20),
+                text("Comparing files of the same size...").size(20),
+                text(format!("Files hashed: {}", progress.files_seen)).size(20),
+                text(format!("Bytes read: {}", human_readable_size(progress.bytes_counted))).size(20),
+                text(format!("Elapsed: {:.1} s", progress.elapsed.as_secs_f64())).size(20),
+                button("Cancel")
+                    .on_press(Message::CancelDuplicates)
+                    .padding(20)
+                    .style(grey_button_style),
+            ]
+            .spacing(10)
+            .padding(20)
+            .into();
+        };
+
+        let (unkept, unkept_size) = self.unkept_duplicates();
+        let idle = !unkept.is_empty() && self.pending_delete.is_empty() && self.pending_link.is_none();
+        let delete_button = button(text(format!("Delete unticked copies ({} files, {})", unkept.len(), human_readable_size(unkept_size))))
+            .on_press_maybe(idle.then_some(Message::DeleteUnkeptDuplicates))
+            .padding(20)
+            .style(grey_button_style);
+        // Same space back without losing any path: the unticked copies point at a ticked one instead
+        let link_buttons = row([LinkMode::Hardlink, LinkMode::Reflink].map(|mode| {
+            button(text(format!("{} instead", mode)))
+                .on_press_maybe(idle.then_some(Message::LinkUnkeptDuplicates(mode)))
+                .padding(20)
+                .style(grey_button_style)
+                .into()
+        }))
+        .spacing(10);
+
+        let shown = self.shown_duplicate_groups();
+        let summary = if shown.len() < duplicates.groups.len() {
+            format!(
+                "{} groups, {} reclaimable, showing the {} largest",
+                duplicates.groups.len(),
+                human_readable_size(duplicates.reclaimable()),
+                shown.len(),
+            )
+        } else {
+            format!("{} groups, {} reclaimable", duplicates.groups.len(), human_readable_size(duplicates.reclaimable()))
+        };
+
+        let list = scrollable(
+            column(shown.iter().map(|group| {
+                let kept = group.files.iter().filter(|file| self.keep.contains(*file)).count();
+                let title = format!(
+                    "{} copies of {}, {} reclaimable{}",
+                    group.files.len(),
+                    human_readable_size(group.size),
+                    human_readable_size(group.reclaimable()),
+                    if kept == 0 { "  (tick at least one copy to keep, nothing here gets deleted until then)" } else { "" },
+                );
+                container(
+                    column![text(title).size(18)]
+                        .extend(group.files.iter().map(|file| {
+                            let path = file.clone();
+                            row![
+                                checkbox("Keep", self.keep.contains(file))
+                                    .on_toggle(move |keep| Message::SetKeep(path.clone(), keep)),
+                                horizontal_space().width(20),
+                                text(file.to_string_lossy().to_string()),
+                            ]
+                            .into()
+                        }))
+                        .spacing(5),
+                )
+                .padding(10)
+                .width(Length::Fill)
+                .style(container::rounded_box)
+                .into()
+            }))
+            .spacing(10)
+        )
+        .height(Length::Fill);
+
+        column![
+            header,
+            row![
+                text(summary).size(20),
+                horizontal_space(),
+                button("Search Again")
+                    .on_press(Message::FindDuplicates)
+                    .padding(20)
+                    .style(grey_button_style),
+                horizontal_space().width(10),
+                delete_button,
+                horizontal_space().width(10),
+                link_buttons,
+            ]
+            .align_y(Center),
+            text(&self.action_status).size(16),
+            self.view_confirm_delete(),
+            self.view_confirm_link(),
+            horizontal_rule(20),
+            list,
+        ]
+        .spacing(10)
+        .padding(20)
+        .into()
+    }
+
+    // Empty folders and zero-byte files under the scanned root, removed in bulk after asking
+    fn view_empty(&self) -> Element<'_, Message> {
+        let none = EmptyEntries::default();
+        let empty = self.empty.as_ref().unwrap_or(&none);
+        let selected = empty.folders.iter().chain(&empty.files).filter(|path| self.empty_selected.contains(*path)).count();
+
+        let section = |title: String, paths: &[PathBuf]| -> Element<'_, Message> {
+            column![text(title).size(20)]
+                .extend(paths.iter().map(|path| {
+                    let target = path.clone();
+                    checkbox(path.to_string_lossy().to_string(), self.empty_selected.contains(path))
+                        .on_toggle(move |selected| Message::SetEmptySelected(target.clone(), selected))
+                        .into()
+                }))
+                .spacing(5)
+                .into()
+        };
+
+        let list = scrollable(
+            column![
+                section(format!("Empty folders ({})", empty.folders.len()), &empty.folders),
+                vertical_space().height(20),
+                section(format!("Zero-byte files ({})", empty.files.len()), &empty.files),
+            ]
+        )
+        .height(Length::Fill);
+
+        column![
+            row![
+                button("Go Back")
+                    .on_press(Message::CloseEmpty)
+                    .padding(20)
+                    .style(grey_button_style),
+                horizontal_space().width(20),
+                text("Empty Items").size(30),
+                horizontal_space(),
+                button("Select All")
+                    .on_press(Message::SelectAllEmpty(true))
+                    .padding(20)
+                    .style(grey_button_style),
+                horizontal_space().width(10),
+                button("Select None")
+                    .on_press(Message::SelectAllEmpty(false))
+                    .padding(20)
+                    .style(grey_button_style),
+                horizontal_space().width(10),
+                button(text(format!("Remove Selected ({})", selected)))
+                    .on_press_maybe((selected > 0 && self.pending_delete.is_empty()).then_some(Message::RemoveSelectedEmpty))
+                    .padding(20)
+                    .style(grey_button_style),
+            ]
+            .align_y(Center),
+            text("Empty folders only hold other empty folders, they are removed with everything in them. \
+                Some tools need their empty marker files (.gitkeep, __init__.py), leave those unticked.").size(16),
+            text(&self.action_status).size(16),
+            self.view_confirm_delete(),
+            horizontal_rule(20),
+            list,
+        ]
+        .spacing(10)
+        .padding(20)
+        .into()
+    }
+
+    // Symlinks under the scanned root with where they point, broken ones first.
+    // Each can be removed or pointed somewhere else.
+    fn view_symlinks(&self) -> Element<'_, Message> {
+        let links = self.symlinks.as_deref().unwrap_or(&[]);
+        let broken: Vec<PathBuf> = links.iter().filter(|link| link.is_broken()).map(|link| link.path.clone()).collect();
+        let idle = self.pending_delete.is_empty();
+
+        let rows = links.iter().map(|link| {
+            let current = link.target.to_string_lossy().to_string();
+            let edited = self.symlink_targets.get(&link.path);
+            let changed = edited.is_some_and(|target| !target.trim().is_empty() && *target != current);
+            let path = link.path.clone();
+            row![
+                text(link.status.to_string()).width(100).color(link_status_color(link.status)),
+                text(link.path.to_string_lossy().to_string()).width(Length::FillPortion(3)),
+                text_input("new target", edited.unwrap_or(&current))
+                    .on_input(move |target| Message::SetSymlinkTarget(path.clone(), target))
+                    .on_submit_maybe(changed.then(|| Message::RetargetSymlink(link.path.clone())))
+                    .width(Length::FillPortion(3)),
+                button("Retarget")
+                    .on_press_maybe(changed.then(|| Message::RetargetSymlink(link.path.clone())))
+                    .style(grey_button_style),
+                button("Remove")
+                    .on_press_maybe(idle.then(|| Message::RemoveSymlinks(vec![link.path.clone()])))
+                    .style(grey_button_style),
+            ]
+            .spacing(10)
+            .align_y(Center)
+            .into()
+        });
+
+        column![
+            row![
+                button("Go Back")
+                    .on_press(Message::CloseSymlinks)
+                    .padding(20)
+                    .style(grey_button_style),
+                horizontal_space().width(20),
+                text("Symlinks").size(30),
+                horizontal_space(),
+                button(text(format!("Remove All Broken ({})", broken.len())))
+                    .on_press_maybe((!broken.is_empty() && idle).then_some(Message::RemoveSymlinks(broken)))
+                    .padding(20)
+                    .style(grey_button_style),
+            ]
+            .align_y(Center),
+            text(format!(
+                "{} symlinks, {} broken. Removing a link leaves its target alone. \
+                A relative target is relative to the folder the link is in.",
+                links.len(),
+                links.iter().filter(|link| link.is_broken()).count(),
+            ))
+            .size(16),
+            text(&self.action_status).size(16),
+            self.view_confirm_delete(),
+            horizontal_rule(20),
+            scrollable(column![].extend(rows).spacing(5)).height(Length::Fill),
+        ]
+        .spacing(10)
+        .padding(20)
+        .into()
+    }
+
+    // Only this many groups are listed, and only listed groups are ever deleted from
+    fn shown_duplicate_groups(&self) -> &[DuplicateGroup] {
+        let groups = self.duplicates.as_ref().map_or(&[][..], |duplicates| &duplicates.groups[..]);
+        &groups[..groups.len().min(MAX_DUPLICATE_GROUPS)]
+    }
+
+    // Every copy that isn't ticked, from groups that still keep at least one, and what they add up to
+    fn unkept_duplicates(&self) -> (Vec<PathBuf>, u64) {
+        let mut unkept = Vec::new();
+        let mut size = 0;
+        for group in self.shown_duplicate_groups() {
+            if !group.files.iter().any(|file| self.keep.contains(file)) {
+                continue;
+            }
+            for file in group.files.iter().filter(|file| !self.keep.contains(*file)) {
+                unkept.push(file.clone());
+                size += group.size;
+            }
+        }
+        (unkept, size)
+    }
+
+    // Asks before anything in pending_delete is removed, empty when nothing is pending
+    fn view_confirm_delete(&self) -> Element<'_, Message> {
+        if self.pending_delete.is_empty() {
+            return text("").into();
+        }
+        confirm_banner(
+            format!("Delete {} files? This can't be undone.", self.pending_delete.len()),
+            "Delete",
+            Message::ConfirmDelete,
+        )
+    }
+
+    fn view_confirm_link(&self) -> Element<'_, Message> {
+        let Some(mode) = self.pending_link else {
+            return text("").into();
+        };
+        let question = match mode {
+            LinkMode::Hardlink => "Replace the unticked copies with hardlinks to the ticked one? Editing any of them will change all of them.",
+            LinkMode::Reflink => "Replace the unticked copies with copy-on-write clones of the ticked one? Needs btrfs, xfs or similar.",
+        };
+        confirm_banner(question.to_string(), "Replace", Message::ConfirmLink)
+    }
+
+    // Size change of `path` in diff mode: red for growth, green for space freed
+    fn delta_text(&self, path: &Path) -> Element<'_, Message> {
+        let Some(delta) = self.diff.as_ref().and_then(|diff| diff.get(path)) else {
+            return text("").into();
+        };
+
+        let label = match delta.change() {
+            Change::Added => format!("new {}", human_readable_delta(delta.delta(self.show_allocated))),
+            Change::Unchanged => return text("").into(),
+            _ => human_readable_delta(delta.delta(self.show_allocated)),
+        };
+        text(label).color(delta_color(delta.delta(self.show_allocated))).into()
+    }
+
+    // Entries of the current folder that only the older snapshot had
+    fn removed_rows(&self) -> Vec<Element<'_, Message>> {
+        let (Some(diff), Some(current_path)) = (&self.diff, &self.selected_path) else {
+            return Vec::new();
+        };
+
+        diff.removed_in(current_path)
+            .into_iter()
+            .map(|delta| {
+                let name = delta.path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+                row![
+                    text(name).width(Length::FillPortion(3)),
+                    text(format!("removed {}", human_readable_delta(delta.delta(self.show_allocated))))
+                        .color(delta_color(-1)),
+                ]
+                .padding(15)
+                .spacing(10)
+                .into()
+            })
+            .collect()
+    }
+
     fn theme(&self) -> Theme {
         self.theme.clone()
     }
 
-    fn load_folder_contents(&mut self, path: &PathBuf) {
-        self.selected_path = Some(path.clone());
+    // Recursive size of a node according to the apparent size / disk usage toggle
+    fn node_size(&self, node: &DirNode) -> u64 {
+        if self.show_allocated {
+            node.allocated_size
+        } else {
+            node.total_size
+        }
+    }
+
+    fn folder_stats_lines(&self, stats: &FolderStats) -> Vec<String> {
+        let (size_label, size) = if self.show_allocated {
+            ("Disk usage", stats.allocated_size)
+        } else {
+            ("Apparent size", stats.total_size)
+        };
+
+        vec![
+            format!("Name: {}", stats.file_name),
+            format!("{}: {}", size_label, human_readable_size(size)),
+            format!("Total size: {}", human_readable_size(stats.total_size)),
+            format!("Allocated size: {}", human_readable_size(stats.allocated_size)),
+            format!("Item count: {}", stats.item_count),
+            format!("File count: {}", stats.file_count),
+            format!("Subdirectory count: {}", stats.subdir_count),
+            format!("Inode count: {}", stats.inode_count),
+            format!("Shared via hardlinks: {}", human_readable_size(stats.shared_size)),
+            format!("Extra hardlinks skipped: {}", stats.extra_links),
+            format!("Excluded size: {}", human_readable_size(stats.excluded_size)),
+            format!("Unreadable paths: {}", stats.error_count),
+            format!("Last modified: {}", format_time(stats.last_modified)),
+        ]
+    }
+
+    // Make `tree` the one every page browses, starting at its root
+    fn show_tree(&mut self, mut tree: DirTree, root: &Path, scanned_at: SystemTime, from_snapshot: bool) {
+        tree.sort_children(self.scan_options.sort_order);
+        self.tree = Some(tree);
+        self.diff = None;
+        // Duplicate groups belong to the previous tree
+        if let Some(control) = self.duplicate_control.take() {
+            control.cancel();
+        }
+        self.duplicates = None;
+        self.empty = None;
+        self.symlinks = None;
+        self.pending_delete.clear();
+        self.pending_link = None;
+        self.action_status.clear();
+        self.root_path = Some(root.to_path_buf());
+        self.scanned_at = Some(scanned_at);
+        self.from_snapshot = from_snapshot;
+        self.current_page = Page::Normal;
+        self.load_folder_contents(root);
+    }
+
+    // Remove one file, or a folder of nothing but empty folders, from disk and from the tree.
+    // False (and a log line) if it couldn't be deleted.
+    fn delete_file(&mut self, full_path: &Path) -> bool {
+        println!("Attempting to delete: {}", full_path.display());
+
+        // Attempt to delete the file
+        let is_folder = std::fs::symlink_metadata(full_path).is_ok_and(|meta| meta.is_dir());
+        let removed = if is_folder { remove_empty_folder(full_path) } else { std::fs::remove_file(full_path) };
+        if let Err(e) = removed {
+            println!("Failed to delete file: {}", e);
+            return false;
+        }
+        //remove from the tree and the list
+        if let Some(tree) = &mut self.tree {
+            if let Some(id) = tree.find(full_path) {
+                tree.remove(id);
+            }
+        }
+        self.chart_cache.clear();
+        true
+    }
+
+    // Every live scan adds a point to its top-level folders' size history, and to every folder that changed.
+    // The file is appended to on another thread, the returned task reports how that went.
+    fn record_history(&mut self, tree: &DirTree, scanned_at: SystemTime) -> Task<Message> {
+        let points = self.history_index.scan_points(tree, scanned_at);
+        let write = match self.history.clone() {
+            Some(history) => {
+                let points = points.clone();
+                blocking_task(
+                    move || history.append(&points).map_err(|e| format!("{}: {}", history.file().display(), e)),
+                    Message::HistoryWritten,
+                )
+            }
+            None => Task::none(),
+        };
+        self.history_index.add_points(points);
+        write
+    }
+
+    // The whole tree with the options it was scanned with, so it can be reopened without rescanning
+    fn save_snapshot(&self) {
+        let (Some(tree), Some(scanned_at)) = (&self.tree, self.scanned_at) else { return };
+
+        let file_name = format!("{}.{}", tree.node(tree.root()).entry.name, SNAPSHOT_EXTENSION);
+        let Some(target) = FileDialog::new()
+            .set_file_name(file_name)
+            .add_filter("DiskScanner snapshot", &[SNAPSHOT_EXTENSION])
+            .save_file()
+        else {
+            return;
+        };
+
+        let snapshot = Snapshot::new(tree.clone(), self.scan_options.clone(), scanned_at);
+        match save_snapshot(&target, &snapshot) {
+            Ok(()) => println!("Saved snapshot to {}", target.display()),
+            Err(e) => println!("Failed to save snapshot: {}", e),
+        }
+    }
+
+    // Save the current folder's totals, its drive and every entry below it; the format follows the extension
+    fn export_current_folder(&self) {
+        let (Some(tree), Some(current_path)) = (&self.tree, &self.selected_path) else { return };
+        let Some(id) = tree.find(current_path) else { return };
+
+        let file_name = format!("{}.json", tree.node(id).entry.name);
+        let mut dialog = FileDialog::new().set_file_name(file_name);
+        for format in ExportFormat::ALL {
+            dialog = dialog.add_filter(format.to_string(), &[format.extension()]);
+        }
+        dialog = dialog.add_filter("ncdu export", &["ncdu"]);
+        let Some(target) = dialog.save_file() else { return };
+
+        let usage = if self.offline { None } else { drive_usage(current_path).ok() };
+        let entries = tree.descendants(id).into_iter().skip(1).map(|child| &tree.node(child).entry);
+
+        let result = std::fs::File::create(&target).and_then(|file| {
+            let writer = std::io::BufWriter::new(file);
+            if target.extension().is_some_and(|extension| extension == "ncdu") {
+                return write_ncdu(writer, tree, id);
+            }
+            let format = ExportFormat::from_path(&target).unwrap_or_default();
+            write_scan(writer, &tree.folder_stats(id), usage.as_ref(), entries, format)
+        });
+        match result {
+            Ok(()) => println!("Exported {} to {}", current_path.display(), target.display()),
+            Err(e) => println!("Failed to export: {}", e),
+        }
+    }
+
+    fn load_folder_contents(&mut self, path: &Path) {
+        self.selected_path = Some(path.to_path_buf());
+        self.chart_cache.clear();
         
-        // Avoid scanning directories repeatedly
-        if let Some(stats) = compute_folder_stats(path) {
+        // The tree already holds every folder, nothing gets rescanned here
+        let node = self.tree.as_ref().and_then(|tree| tree.find(path));
+        if let (Some(tree), Some(id)) = (&self.tree, node) {
+            let stats = tree.folder_stats(id);
             // Update description with detailed stats
-            self.description = vec![
-                format!("Name: {}", stats.file_name),
-                format!("Total size: {}", stats.total_size),
-                format!("Allocated size: {}", stats.allocated_size),
-                format!("Item count: {}", stats.item_count),
-                format!("File count: {}", stats.file_count),
-                format!("Subdirectory count: {}", stats.subdir_count),
-                format!("Last modified: {}", stats.last_modified),
-            ];
+            self.description = self.folder_stats_lines(&stats);
+            if let Some(diff) = &self.diff {
+                self.description.extend(diff_lines(diff, path, self.show_allocated));
+            }
+            self.folder_stats = Some(stats);
     
             // Update total stats for the view
-            let (drive_usage_info, usage_percentage) = drive_usage(path);
-            self.total = drive_usage_info;
+            let usage = if self.offline {
+                Err("not available for an imported scan".to_string())
+            } else {
+                drive_usage(path).map_err(|e| e.to_string())
+            };
+            match usage {
+                Ok(usage) => {
+                    self.total = drive_usage_lines(&usage);
+                    self.usage_percentage = usage.used_percentage(); // Store the usage percentage for the progress bar
+                }
+                Err(e) => {
+                    self.total = vec![format!("Could not retrieve drive usage: {}", e)];
+                    self.usage_percentage = 0.0;
+                }
+            }
 
         } else {
+            self.folder_stats = None;
             self.description = vec!["Failed to compute folder stats".to_string()];
             self.total = vec!["Failed to compute total stats".to_string()];
         }
-    
-        // Cache the file list only when the path changes
-        self.files_name = scan_directory(path);
+
+        self.items = match (&self.tree, node) {
+            (Some(tree), Some(id)) => tree.children(id).to_vec(),
+            _ => Vec::new(),
+        };
+        self.selected_index = None;
         self.path_name = path.to_string_lossy().to_string();
     }
     
-}
\ No newline at end of file
+}
+
+fn file_entry_lines(entry: &FileEntry) -> Vec<String> {
+    vec![
+        format!("Name : {} ", entry.name),
+        format!("Type: {:?}", entry.file_type),
+        format!("Size: {}", human_readable_size(entry.size)),
+        format!("Allocated size: {}", human_readable_size(entry.allocated_size)),
+        format!("Permissions: {} ({:o})", human_readable_mode(entry.mode), entry.mode & 0o7777),
+        format!("Owner: {}:{}", entry.uid, entry.gid),
+        format!("Inode: {}", entry.inode),
+        format!("Hardlinks: {}", entry.nlink),
+        format!("Last Modified: {}", format_time(entry.modified)),
+        format!("Last Accessed: {}", format_time(entry.accessed)),
+        format!("Status Changed: {}", format_time(entry.changed)),
+    ]
+}
+
+// What changed in `path` since the older snapshot, for the description panel
+fn diff_lines(diff: &TreeDiff, path: &Path, allocated: bool) -> Vec<String> {
+    let mut lines = vec![
+        String::new(),
+        format!("Compared with the scan of {}", format_time(diff.old_scanned_at)),
+    ];
+    if let Some(delta) = diff.get(path) {
+        lines.push(format!("Change: {}", human_readable_delta(delta.delta(allocated))));
+    }
+    lines.push(format!(
+        "Files added: {}, removed: {} (whole scan)",
+        diff.added_files().count(),
+        diff.removed_files().count()
+    ));
+    lines.push("Biggest growth:".to_string());
+    for delta in diff.biggest_growth(5, allocated) {
+        lines.push(format!("  {} {}", human_readable_delta(delta.delta(allocated)), delta.path.display()));
+    }
+    lines
+}
+
+fn link_status_color(status: LinkStatus) -> Color {
+    match status {
+        LinkStatus::Valid | LinkStatus::Ancestor => Color::from_rgb(0.3, 0.8, 0.3),
+        LinkStatus::Dangling | LinkStatus::Loop => Color::from_rgb(0.9, 0.3, 0.3),
+        LinkStatus::Unreadable => Color::from_rgb(0.9, 0.6, 0.2),
+    }
+}
+
+fn delta_color(delta: i64) -> Color {
+    if delta > 0 {
+        Color::from_rgb(0.9, 0.3, 0.3)
+    } else {
+        Color::from_rgb(0.3, 0.8, 0.3)
+    }
+}
+
+fn format_time(time: Option<std::time::SystemTime>) -> String {
+    time.map(human_readable_time).unwrap_or_else(|| "Unknown".to_string())
+}
+
+// "node_modules, *.log" -> ["node_modules", "*.log"]
+fn split_patterns(input: &str) -> Vec<String> {
+    input
+        .split(',')
+        .map(|pattern| pattern.trim().to_string())
+        .filter(|pattern| !pattern.is_empty())
+        .collect()
+}
+
+fn drive_usage_lines(usage: &DriveUsage) -> Vec<String> {
+    vec![
+        "Drive Usage:".to_string(),
+        format!("Mount Point: {}", usage.mount_point.display()),
+        format!("Total Space: {}", human_readable_size(usage.total_space)),
+        format!(
+            "Used Space: {} ({:.2}%)",
+            human_readable_size(usage.used_space()),
+            usage.used_percentage()
+        ),
+        format!(
+            "Free Space: {} ({:.2}%)",
+            human_readable_size(usage.free_space),
+            usage.free_percentage()
+        ),
+        format!("Available Space: {}", human_readable_size(usage.available_space)),
+        format!("Reserved Space: {}", human_readable_size(usage.reserved_space)),
+        format!("Total Inodes: {}", usage.total_inodes),
+        format!(
+            "Used Inodes: {} ({:.2}%)",
+            usage.used_inodes(),
+            usage.inode_percentage()
+        ),
+        format!("Free Inodes: {}", usage.free_inodes),
+    ]
+}
+
+// One selectable card per mounted filesystem on the home page
+fn mount_card(mount: &MountEntry) -> Element<'_, Message> {
+    let (summary, percentage) = match &mount.usage {
+        Some(usage) => (
+            format!(
+                "{} used of {} ({:.1}%)",
+                human_readable_size(usage.used_space()),
+                human_readable_size(usage.total_space),
+                usage.used_percentage()
+            ),
+            usage.used_percentage(),
+        ),
+        None => ("Usage unavailable".to_string(), 0.0),
+    };
+
+    button(
+        column![
+            text(mount.mount_point.to_string_lossy().to_string()).size(20),
+            text(format!(
+                "{} · {}{}",
+                mount.source,
+                mount.fs_type,
+                if mount.is_read_only() { " · read-only" } else { "" }
+            ))
+            .size(14),
+            progress_bar(0.0..=100.0, percentage as f32)
+                .width(300)
+                .height(10),
+            text(summary).size(14),
+        ]
+        .spacing(5),
+    )
+    .on_press(Message::FolderSelected(Some(mount.mount_point.clone())))
+    .padding(15)
+    .width(Length::Fill)
+    .style(grey_button_style)
+    .into()
+}
+
+// A question with a confirm and a cancel button, cancel drops whatever is pending
+fn confirm_banner<'a>(question: String, confirm: &'a str, on_confirm: Message) -> Element<'a, Message> {
+    container(
+        row![
+            text(question).size(20),
+            horizontal_space(),
+            button(confirm)
+                .on_press(on_confirm)
+                .padding(15)
+                .style(button::danger),
+            horizontal_space().width(10),
+            button("Cancel")
+                .on_press(Message::CancelDelete)
+                .padding(15)
+                .style(grey_button_style),
+        ]
+        .align_y(Center),
+    )
+    .padding(10)
+    .width(Length::Fill)
+    .style(container::rounded_box)
+    .into()
+}
+
+fn grey_button_style(theme: &Theme, status: button::Status) -> button::Style {
+    match status {
+        button::Status::Active => {
+            button::Style::default()
+            .with_background(Color::from_rgb(0.5, 0.5, 0.5))
+        }
+        button::Status::Hovered => {
+            button::Style::default()
+            .with_background(Color::WHITE)
+        }
+        button::Status::Pressed => {
+            button::Style::default()
+            .with_background(Color::from_rgb(0.4, 0.8, 0.4))
+        }
+        _ => button::primary(theme, status),
+    }
+}
+
+// Hash the same-size groups on worker threads, reporting progress like a scan does
+fn duplicates_task(id: u64, groups: Vec<DuplicateGroup>, control: ScanControl) -> Task<Message> {
+    background_task(
+        control,
+        move |control| confirm_duplicates(groups, control),
+        move |progress| Message::DuplicatesProgress(id, progress),
+        move |duplicates| Message::DuplicatesFound(id, duplicates),
+    )
+}
+
+// Runs the walk on its own thread and streams progress back until it's done or cancelled
+fn scan_task(id: u64, path: PathBuf, options: ScanOptions, control: ScanControl) -> Task<Message> {
+    background_task(
+        control,
+        move |control| scan_tree(&path, &options, control),
+        move |progress| Message::ScanProgress(id, progress),
+        move |tree| Message::ScanFinished(id, tree),
+    )
+}
+
+// Runs blocking `work` on its own thread so the UI keeps drawing, then sends `done` with what it returned
+fn blocking_task<T: Send + 'static>(
+    work: impl FnOnce() -> T + Send + 'static,
+    done: impl FnOnce(T) -> Message + Send + 'static,
+) -> Task<Message> {
+    let (sender, receiver) = iced::futures::channel::oneshot::channel();
+    std::thread::spawn(move || {
+        let _ = sender.send(work());
+    });
+    // A worker that panicked sends nothing, and then there is nothing to report
+    Task::future(async move { receiver.await.ok().map(done) }).and_then(Task::done)
+}
+
+// Runs `work` on its own thread, sends `progress` every 100 ms until `control` is finished or cancelled,
+// then `done` with the result (None when cancelled)
+fn background_task<T: Send + 'static>(
+    control: ScanControl,
+    work: impl FnOnce(&ScanControl) -> Option<T> + Send + 'static,
+    progress: impl Fn(ScanProgress) -> Message + Send + 'static,
+    done: impl FnOnce(Option<T>) -> Message + Send + 'static,
+) -> Task<Message> {
+    Task::run(
+        iced::stream::channel(16, move |mut output| async move {
+            let (done_sender, done_receiver) = iced::futures::channel::oneshot::channel();
+            let worker = control.clone();
+            std::thread::spawn(move || {
+                let _ = done_sender.send(work(&worker));
+            });
+
+            let mut progress_output = output.clone();
+            std::thread::spawn(move || {
+                while !control.is_finished() && !control.is_cancelled() {
+                    std::thread::sleep(Duration::from_millis(100));
+                    if iced::futures::executor::block_on(progress_output.send(progress(control.progress()))).is_err() {
+                        break;
+                    }
+                }
+            });
+
+            let result = done_receiver.await.ok().flatten();
+            let _ = output.send(done(result)).await;
+        }),
+        |message| message,
+    )
+}
@@ -1,7 +1,8 @@
-use std::fs;
-use std::path::PathBuf;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::io::{self, Write};
 
 
 pub fn prompt_and_open_folder(path: &PathBuf) {
@@ -25,7 +26,7 @@ fn open_folder(path: &PathBuf) {
     } else if cfg!(target_os = "linux") {
         Command::new("xdg-open").arg(path).status()
     } else {
-        Err(io::Error::new(io::ErrorKind::Other, "Unsupported OS"))
+        Err(io::Error::other("Unsupported OS"))
     };
 
     match result {
@@ -49,3 +50,346 @@ pub fn prompt_and_delete_file(file_path: &PathBuf) {
         println!("File will not be deleted.");
     }
 }
+
+// Remove a folder holding nothing but empty folders, innermost first.
+// Only ever fs::remove_dir, so anything that shows up in there since the scan stops it.
+pub fn remove_empty_folder(path: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            return Err(io::Error::new(io::ErrorKind::DirectoryNotEmpty, format!("{} is not empty", path.display())));
+        }
+        remove_empty_folder(&entry.path())?;
+    }
+    fs::remove_dir(path)
+}
+
+// How a duplicate gets to share its content with the copy that is kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkMode {
+    Hardlink, // one inode for both paths, same filesystem only; an edit through either path shows in both
+    Reflink,  // a copy-on-write clone (FICLONE: btrfs, xfs, bcachefs), both stay separate files
+}
+
+impl fmt::Display for LinkMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LinkMode::Hardlink => "Hardlink",
+            LinkMode::Reflink => "Reflink",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// What replacing a set of duplicates did
+#[derive(Debug, Clone, Default)]
+pub struct LinkReport {
+    pub replaced: Vec<PathBuf>,
+    pub reclaimed: u64, // disk space freed, as far as the filesystem reports it
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+// Replace every file of `duplicates` with a link to `keep`, one at a time, stopping at nothing
+pub fn link_duplicates(keep: &Path, duplicates: &[PathBuf], mode: LinkMode) -> LinkReport {
+    let mut report = LinkReport::default();
+    for duplicate in duplicates.iter().filter(|duplicate| duplicate.as_path() != keep) {
+        match replace_with_link(keep, duplicate, mode) {
+            Ok(reclaimed) => {
+                report.replaced.push(duplicate.clone());
+                report.reclaimed += reclaimed;
+            }
+            Err(e) => report.failed.push((duplicate.clone(), e.to_string())),
+        }
+    }
+    report
+}
+
+// Make `duplicate` a hardlink or reflink of `keep` after checking, byte for byte, that both hold the same data.
+// The link is made next to `duplicate` and renamed over it, so a failure leaves the original in place.
+// Returns the bytes freed: nothing when `duplicate` has other hardlinks still holding on to its data.
+pub fn replace_with_link(keep: &Path, duplicate: &Path, mode: LinkMode) -> io::Result<u64> {
+    // Not following links: a hardlink to a symlink would link the symlink itself
+    let keep_meta = fs::symlink_metadata(keep)?;
+    let duplicate_meta = fs::symlink_metadata(duplicate)?;
+    if !keep_meta.is_file() || !duplicate_meta.is_file() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "only regular files can be linked"));
+    }
+    if same_inode(&keep_meta, &duplicate_meta) {
+        return Ok(0);
+    }
+    if keep_meta.len() != duplicate_meta.len() || !same_content(keep, duplicate)? {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "contents differ"));
+    }
+
+    // Nothing may have touched the duplicate while it was being compared
+    let checked = fs::symlink_metadata(duplicate)?;
+    if checked.len() != duplicate_meta.len() || checked.modified().ok() != duplicate_meta.modified().ok() {
+        return Err(io::Error::other("file changed while it was being compared"));
+    }
+
+    let file_name = duplicate.file_name().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no file name"))?;
+    let temporary = duplicate.with_file_name(format!(".{}.diskscanner-link-{}", file_name.to_string_lossy(), std::process::id()));
+    let linked = match mode {
+        // `keep` may have been swapped for something else since it was checked, the new link has to be the file compared
+        LinkMode::Hardlink => fs::hard_link(keep, &temporary).and_then(|_| {
+            let linked = fs::symlink_metadata(&temporary)?;
+            if linked.is_file() && same_inode(&linked, &keep_meta) {
+                Ok(())
+            } else {
+                Err(io::Error::other("file changed while it was being compared"))
+            }
+        }),
+        LinkMode::Reflink => reflink(keep, &temporary, &duplicate_meta),
+    };
+    if let Err(e) = linked.and_then(|_| fs::rename(&temporary, duplicate)) {
+        let _ = fs::remove_file(&temporary);
+        return Err(e);
+    }
+
+    Ok(if link_count(&duplicate_meta) > 1 { 0 } else { allocated_size(&duplicate_meta) })
+}
+
+// Point the symlink at `link` to `target` instead, stored as given (a relative target is relative to the link's folder).
+// The new link is made next to the old one and renamed over it, so a failure leaves the old link in place.
+pub fn retarget_symlink(link: &Path, target: &Path) -> io::Result<()> {
+    if !fs::symlink_metadata(link)?.file_type().is_symlink() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not a symlink", link.display())));
+    }
+    if target.as_os_str().is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "no target given"));
+    }
+
+    let file_name = link.file_name().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no file name"))?;
+    let temporary = link.with_file_name(format!(".{}.diskscanner-link-{}", file_name.to_string_lossy(), std::process::id()));
+    if let Err(e) = make_symlink(target, &temporary).and_then(|_| fs::rename(&temporary, link)) {
+        let _ = fs::remove_file(&temporary);
+        return Err(e);
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn make_symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+// Windows needs to know whether the link is to a folder, a dangling target is taken to be a file
+#[cfg(windows)]
+fn make_symlink(target: &Path, link: &Path) -> io::Result<()> {
+    let resolved = link.parent().map(|folder| folder.join(target)).unwrap_or_else(|| target.to_path_buf());
+    if resolved.is_dir() {
+        std::os::windows::fs::symlink_dir(target, link)
+    } else {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn make_symlink(_target: &Path, _link: &Path) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "symlinks are not supported on this platform"))
+}
+
+fn same_content(a: &Path, b: &Path) -> io::Result<bool> {
+    let mut a = BufReader::with_capacity(64 * 1024, File::open(a)?);
+    let mut b = BufReader::with_capacity(64 * 1024, File::open(b)?);
+    loop {
+        let chunk_a = a.fill_buf()?;
+        let chunk_b = b.fill_buf()?;
+        if chunk_a.is_empty() || chunk_b.is_empty() {
+            return Ok(chunk_a.is_empty() && chunk_b.is_empty());
+        }
+        let length = chunk_a.len().min(chunk_b.len());
+        if chunk_a[..length] != chunk_b[..length] {
+            return Ok(false);
+        }
+        a.consume(length);
+        b.consume(length);
+    }
+}
+
+// A copy-on-write clone of `source` at `target`, with the permissions `like` had
+#[cfg(target_os = "linux")]
+fn reflink(source: &Path, target: &Path, like: &fs::Metadata) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let source = File::open(source)?;
+    let target_file = OpenOptions::new().write(true).create_new(true).open(target)?;
+    // SAFETY: both descriptors are open for as long as the call runs
+    if unsafe { libc::ioctl(target_file.as_raw_fd(), libc::FICLONE, source.as_raw_fd()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    target_file.set_permissions(like.permissions())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn reflink(_source: &Path, _target: &Path, _like: &fs::Metadata) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "reflinks are only supported on Linux"))
+}
+
+#[cfg(unix)]
+fn same_inode(a: &fs::Metadata, b: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+#[cfg(not(unix))]
+fn same_inode(_a: &fs::Metadata, _b: &fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn link_count(meta: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    meta.nlink()
+}
+
+#[cfg(not(unix))]
+fn link_count(_meta: &fs::Metadata) -> u64 {
+    1
+}
+
+#[cfg(unix)]
+fn allocated_size(meta: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    meta.blocks() * 512
+}
+
+#[cfg(not(unix))]
+fn allocated_size(meta: &fs::Metadata) -> u64 {
+    meta.len()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::fs;
+    use std::io;
+    use std::os::unix::fs::{symlink, MetadataExt};
+    use std::path::Path;
+    use super::super::testdir::TestDir;
+    use super::{link_duplicates, remove_empty_folder, replace_with_link, retarget_symlink, LinkMode};
+
+    fn inode(path: &Path) -> u64 {
+        fs::symlink_metadata(path).unwrap().ino()
+    }
+
+    // Anything a replace left behind next to the files, besides the files themselves
+    fn leftovers(dir: &TestDir) -> Vec<String> {
+        fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|name| name.contains("diskscanner-link"))
+            .collect()
+    }
+
+    #[test]
+    fn hardlinked_duplicates_share_the_inode() {
+        let dir = TestDir::new("link");
+        let keep = dir.file("keep", 10_000, 1);
+        let copy = dir.file("copy", 10_000, 1);
+        let allocated = fs::metadata(&copy).unwrap().blocks() * 512;
+
+        assert_eq!(replace_with_link(&keep, &copy, LinkMode::Hardlink).unwrap(), allocated);
+        assert_eq!(inode(&keep), inode(&copy));
+        assert_eq!(fs::metadata(&keep).unwrap().nlink(), 2);
+        assert!(leftovers(&dir).is_empty());
+
+        // Already one file, nothing left to free
+        assert_eq!(replace_with_link(&keep, &copy, LinkMode::Hardlink).unwrap(), 0);
+    }
+
+    #[test]
+    fn different_files_are_refused() {
+        let dir = TestDir::new("link-refused");
+        let keep = dir.file("keep", 10_000, 1);
+        let other = dir.file("other", 10_000, 2);
+        let shorter = dir.file("shorter", 9_999, 1);
+        let to_keep = dir.path().join("to-keep");
+        symlink(&keep, &to_keep).unwrap();
+
+        for duplicate in [&other, &shorter] {
+            let error = replace_with_link(&keep, duplicate, LinkMode::Hardlink).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            assert_ne!(inode(&keep), inode(duplicate));
+        }
+        // Neither side may be a symlink, even to an identical file
+        let copy = dir.file("copy", 10_000, 1);
+        for (keep, duplicate) in [(&to_keep, &copy), (&copy, &to_keep)] {
+            let error = replace_with_link(keep, duplicate, LinkMode::Hardlink).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        }
+        assert!(fs::symlink_metadata(&to_keep).unwrap().file_type().is_symlink());
+        assert_ne!(inode(&keep), inode(&copy));
+        assert!(leftovers(&dir).is_empty());
+    }
+
+    #[test]
+    fn a_failed_replace_leaves_the_original() {
+        let dir = TestDir::new("link-failed");
+        let keep = dir.file("keep", 10_000, 1);
+        let copy = dir.file("copy", 10_000, 1);
+        let before = inode(&copy);
+
+        for mode in [LinkMode::Hardlink, LinkMode::Reflink] {
+            // A link left over from an earlier run that died halfway is in the way
+            dir.write(&format!(".copy.diskscanner-link-{}", std::process::id()), b"stale");
+            assert!(replace_with_link(&keep, &copy, mode).is_err());
+            assert_eq!(inode(&copy), before);
+            assert_eq!(fs::read(&copy).unwrap(), fs::read(&keep).unwrap());
+            assert!(leftovers(&dir).is_empty(), "{}", mode);
+        }
+    }
+
+    #[test]
+    fn link_duplicates_reports_each_file() {
+        let dir = TestDir::new("link-all");
+        let keep = dir.file("keep", 5000, 1);
+        let copy = dir.file("copy", 5000, 1);
+        let other = dir.file("other", 5000, 2);
+
+        let report = link_duplicates(&keep, &[keep.clone(), copy.clone(), other.clone()], LinkMode::Hardlink);
+        assert_eq!(report.replaced, vec![copy.clone()]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, other);
+        assert_eq!(inode(&keep), inode(&copy));
+        assert_ne!(inode(&keep), inode(&other));
+    }
+
+    #[test]
+    fn retargeting_repoints_only_symlinks() {
+        let dir = TestDir::new("retarget");
+        let file = dir.file("a", 10, 1);
+        dir.file("b", 10, 2);
+        let link = dir.path().join("link");
+        symlink("missing", &link).unwrap();
+
+        retarget_symlink(&link, Path::new("b")).unwrap();
+        assert_eq!(fs::read_link(&link).unwrap(), Path::new("b"));
+        assert_eq!(fs::read(&link).unwrap(), fs::read(dir.path().join("b")).unwrap());
+
+        assert_eq!(retarget_symlink(&file, Path::new("b")).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(retarget_symlink(&link, Path::new("")).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(fs::read_link(&link).unwrap(), Path::new("b"));
+        assert!(leftovers(&dir).is_empty());
+    }
+
+    #[test]
+    fn only_still_empty_folders_are_removed() {
+        let dir = TestDir::new("remove-empty");
+        dir.dir("gone/a/b");
+        dir.dir("gone/c");
+        remove_empty_folder(&dir.path().join("gone")).unwrap();
+        assert!(!dir.path().join("gone").exists());
+
+        // Something was saved in there since the scan
+        dir.dir("kept/a/b");
+        dir.dir("kept/c");
+        dir.write("kept/a/b/new.txt", b"");
+        symlink("nowhere", dir.path().join("kept/c/link")).unwrap();
+        let error = remove_empty_folder(&dir.path().join("kept")).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::DirectoryNotEmpty);
+        assert!(dir.path().join("kept/a/b/new.txt").exists());
+        assert!(fs::symlink_metadata(dir.path().join("kept/c/link")).is_ok());
+
+        assert_eq!(remove_empty_folder(&dir.path().join("missing")).unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+}
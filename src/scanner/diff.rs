@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use super::snapshot::Snapshot;
+use super::tree::{DirTree, NodeId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Added,
+    Removed,
+    Grown,
+    Shrunk,
+    Unchanged,
+}
+
+// One path as it was in the old scan and as it is in the new one.
+// Paths are relative to the scan root, so two scans of a moved or remounted folder still line up.
+#[derive(Debug, Clone)]
+pub struct PathDelta {
+    pub path: PathBuf,
+    pub is_folder: bool,
+    pub old: Option<SizePair>, // None when the path is new
+    pub new: Option<SizePair>, // None when the path is gone
+}
+
+// Recursive sizes of a path in one scan
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SizePair {
+    pub size: u64,           // apparent size
+    pub allocated_size: u64, // disk usage
+}
+
+impl PathDelta {
+    pub fn change(&self) -> Change {
+        match (self.old, self.new) {
+            (None, _) => Change::Added,
+            (_, None) => Change::Removed,
+            (Some(old), Some(new)) if new.size > old.size => Change::Grown,
+            (Some(old), Some(new)) if new.size < old.size => Change::Shrunk,
+            _ => Change::Unchanged,
+        }
+    }
+
+    // new minus old; a removed path counts as shrinking to zero, an added one as growing from it
+    pub fn delta(&self, allocated: bool) -> i64 {
+        let size = |pair: Option<SizePair>| {
+            let pair = pair.unwrap_or_default();
+            if allocated { pair.allocated_size } else { pair.size }
+        };
+        size(self.new) as i64 - size(self.old) as i64
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TreeDiff {
+    pub old_root: PathBuf,
+    pub new_root: PathBuf,
+    pub old_scanned_at: Option<SystemTime>,
+    pub new_scanned_at: Option<SystemTime>,
+    pub deltas: Vec<PathDelta>, // every path of either scan, the root first
+    index: HashMap<PathBuf, usize>,
+    removed_children: HashMap<PathBuf, Vec<usize>>, // relative folder -> removed entries directly in it
+}
+
+// Compare two scans of the same root: `new` is usually the more recent one
+pub fn diff_trees(old: &DirTree, new: &DirTree) -> TreeDiff {
+    let old_paths = relative_paths(old);
+    let mut old_seen = vec![false; old.len()];
+
+    let mut deltas = Vec::with_capacity(new.len());
+    for id in new.descendants(new.root()) {
+        let relative = relative_path(new, id);
+        let old_id = old_paths.get(&relative).copied();
+        if let Some(old_id) = old_id {
+            old_seen[old_id] = true;
+        }
+        deltas.push(PathDelta {
+            is_folder: new.node(id).entry.is_folder,
+            old: old_id.map(|old_id| sizes(old, old_id)),
+            new: Some(sizes(new, id)),
+            path: relative,
+        });
+    }
+
+    // Whatever the new scan didn't see anymore, in the old tree's order
+    let mut removed_children: HashMap<PathBuf, Vec<usize>> = HashMap::new();
+    for id in old.descendants(old.root()) {
+        if old_seen[id] {
+            continue;
+        }
+        let relative = relative_path(old, id);
+        if let Some(parent) = relative.parent() {
+            removed_children.entry(parent.to_path_buf()).or_default().push(deltas.len());
+        }
+        deltas.push(PathDelta {
+            is_folder: old.node(id).entry.is_folder,
+            old: Some(sizes(old, id)),
+            new: None,
+            path: relative,
+        });
+    }
+
+    let index = deltas.iter().enumerate().map(|(i, delta)| (delta.path.clone(), i)).collect();
+    TreeDiff {
+        old_root: old.path(old.root()).to_path_buf(),
+        new_root: new.path(new.root()).to_path_buf(),
+        old_scanned_at: None,
+        new_scanned_at: None,
+        deltas,
+        index,
+        removed_children,
+    }
+}
+
+pub fn diff_snapshots(old: &Snapshot, new: &Snapshot) -> TreeDiff {
+    let mut diff = diff_trees(&old.tree, &new.tree);
+    diff.old_scanned_at = Some(old.scanned_at);
+    diff.new_scanned_at = Some(new.scanned_at);
+    diff
+}
+
+impl TreeDiff {
+    // Look up a path of the new tree (absolute) or a path relative to the root
+    pub fn get(&self, path: &Path) -> Option<&PathDelta> {
+        let relative = path.strip_prefix(&self.new_root).unwrap_or(path);
+        self.index.get(relative).map(|&i| &self.deltas[i])
+    }
+
+    pub fn root(&self) -> &PathDelta {
+        &self.deltas[0]
+    }
+
+    // Entries of `folder` (a path of the new tree) that only the old scan had
+    pub fn removed_in(&self, folder: &Path) -> Vec<&PathDelta> {
+        let relative = folder.strip_prefix(&self.new_root).unwrap_or(folder);
+        self.removed_children
+            .get(relative)
+            .map(|ids| ids.iter().map(|&i| &self.deltas[i]).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn added_files(&self) -> impl Iterator<Item = &PathDelta> {
+        self.deltas.iter().filter(|delta| !delta.is_folder && delta.change() == Change::Added)
+    }
+
+    pub fn removed_files(&self) -> impl Iterator<Item = &PathDelta> {
+        self.deltas.iter().filter(|delta| !delta.is_folder && delta.change() == Change::Removed)
+    }
+
+    // Folders below the root that grew the most, largest first
+    pub fn biggest_growth(&self, limit: usize, allocated: bool) -> Vec<&PathDelta> {
+        let mut grown: Vec<&PathDelta> = self.deltas[1..]
+            .iter()
+            .filter(|delta| delta.is_folder && delta.delta(allocated) > 0)
+            .collect();
+        grown.sort_by_key(|delta| std::cmp::Reverse(delta.delta(allocated)));
+        grown.truncate(limit);
+        grown
+    }
+
+    // Files that appeared, largest first
+    pub fn largest_added(&self, limit: usize, allocated: bool) -> Vec<&PathDelta> {
+        largest(self.added_files(), limit, allocated)
+    }
+
+    // Files that disappeared, largest first
+    pub fn largest_removed(&self, limit: usize, allocated: bool) -> Vec<&PathDelta> {
+        largest(self.removed_files(), limit, allocated)
+    }
+}
+
+fn largest<'a>(deltas: impl Iterator<Item = &'a PathDelta>, limit: usize, allocated: bool) -> Vec<&'a PathDelta> {
+    let mut deltas: Vec<&PathDelta> = deltas.collect();
+    deltas.sort_by_key(|delta| std::cmp::Reverse(delta.delta(allocated).unsigned_abs()));
+    deltas.truncate(limit);
+    deltas
+}
+
+fn sizes(tree: &DirTree, id: NodeId) -> SizePair {
+    let node = tree.node(id);
+    SizePair {
+        size: node.total_size,
+        allocated_size: node.allocated_size,
+    }
+}
+
+fn relative_path(tree: &DirTree, id: NodeId) -> PathBuf {
+    tree.path(id)
+        .strip_prefix(tree.path(tree.root()))
+        .map(Path::to_path_buf)
+        .unwrap_or_default()
+}
+
+// Every reachable node keyed by its path relative to the root, the root itself being ""
+fn relative_paths(tree: &DirTree) -> HashMap<PathBuf, NodeId> {
+    tree.descendants(tree.root())
+        .into_iter()
+        .map(|id| (relative_path(tree, id), id))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+    use std::time::{Duration, SystemTime};
+    use super::super::options::{scan_tree, ScanOptions};
+    use super::super::progress::ScanControl;
+    use super::super::snapshot::{read_snapshot, write_snapshot, Snapshot};
+    use super::super::testdir::TestDir;
+    use super::{diff_snapshots, Change};
+
+    fn snapshot(root: &Path, scanned_at: SystemTime) -> Snapshot {
+        let options = ScanOptions::new();
+        let tree = scan_tree(root, &options, &ScanControl::new()).unwrap();
+        Snapshot::new(tree, options, scanned_at)
+    }
+
+    #[test]
+    fn snapshots_diff_by_relative_path() {
+        let dir = TestDir::new("diff");
+        dir.file("a/grows", 1000, 1);
+        dir.file("a/same", 500, 2);
+        dir.file("b/gone", 3000, 3);
+        let before = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let old = snapshot(dir.path(), before);
+
+        dir.file("a/grows", 6000, 1);
+        fs::remove_file(dir.path().join("b/gone")).unwrap();
+        dir.file("c/new", 8000, 4);
+        let new = snapshot(dir.path(), before + Duration::from_secs(3600));
+
+        // The old side goes through a snapshot file first, like `diskscanner-cli diff` does
+        let mut saved = Vec::new();
+        write_snapshot(&mut saved, &old).unwrap();
+        let old = read_snapshot(saved.as_slice()).unwrap();
+
+        let diff = diff_snapshots(&old, &new);
+        assert_eq!(diff.old_scanned_at, Some(before));
+        let change = |path: &str| diff.get(Path::new(path)).unwrap().change();
+        assert_eq!(change("a/grows"), Change::Grown);
+        assert_eq!(change("a/same"), Change::Unchanged);
+        assert_eq!(change("b/gone"), Change::Removed);
+        assert_eq!(change("c/new"), Change::Added);
+        assert_eq!(diff.get(Path::new("a/grows")).unwrap().delta(false), 5000);
+        // Absolute paths of the new tree work too
+        assert_eq!(diff.get(&dir.path().join("c/new")).unwrap().delta(false), 8000);
+
+        let removed: Vec<_> = diff.removed_in(&dir.path().join("b")).iter().map(|delta| delta.path.clone()).collect();
+        assert_eq!(removed, vec![Path::new("b/gone").to_path_buf()]);
+        assert_eq!(diff.largest_added(5, false)[0].path, Path::new("c/new"));
+        assert_eq!(diff.largest_removed(5, false)[0].path, Path::new("b/gone"));
+
+        let root = diff.root();
+        assert_eq!(root.delta(false), new.tree.node(new.tree.root()).total_size as i64 - old.tree.node(old.tree.root()).total_size as i64);
+        assert!(diff.biggest_growth(5, false).iter().any(|delta| delta.path == Path::new("a")));
+    }
+}
@@ -1,109 +1,189 @@
 use std::fs;
-use std::path::PathBuf;
-use walkdir::WalkDir;
-use super::utils::{human_readable_size, human_readable_time};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use serde::{Deserialize, Serialize};
+use super::options::{scan_tree, ScanOptions};
+use super::progress::ScanControl;
+use super::report::ScanReport;
+use super::tree::DirTree;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FileKind {
+    File,
+    Directory,
+    Symlink,
+    Other, // sockets, fifos, device nodes
+}
+
+impl FileKind {
+    pub fn from_file_type(file_type: fs::FileType) -> Self {
+        if file_type.is_symlink() {
+            FileKind::Symlink
+        } else if file_type.is_dir() {
+            FileKind::Directory
+        } else if file_type.is_file() {
+            FileKind::File
+        } else {
+            FileKind::Other
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct FileEntry {
     pub path: PathBuf,
     pub name: String,
-    pub size: String,
-    pub modified: String,
+    pub size: u64,           // apparent size, like `du --apparent-size`
+    pub allocated_size: u64, // bytes actually allocated on disk
+    pub modified: Option<SystemTime>,
+    pub accessed: Option<SystemTime>,
+    pub changed: Option<SystemTime>, // inode status change (ctime)
+    pub file_type: FileKind,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub dev: u64,
+    pub inode: u64,
+    pub nlink: u64,
     pub depth: u32,       
     pub is_folder: bool,  
 }
 
-#[derive(Debug)]
+impl FileEntry {
+    pub fn from_metadata(path: &Path, file_type: FileKind, depth: u32, metadata: &fs::Metadata) -> Self {
+        let name = path.file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string_lossy().to_string());
+
+        let mut entry = FileEntry {
+            path: path.to_path_buf(),
+            name,
+            size: metadata.len(),
+            allocated_size: allocated_bytes(metadata),
+            modified: metadata.modified().ok(),
+            accessed: metadata.accessed().ok(),
+            changed: None,
+            file_type,
+            mode: 0,
+            uid: 0,
+            gid: 0,
+            dev: 0,
+            inode: 0,
+            nlink: 1,
+            depth,
+            is_folder: file_type == FileKind::Directory,
+        };
+        entry.fill_unix_metadata(metadata);
+        entry
+    }
+
+    #[cfg(unix)]
+    fn fill_unix_metadata(&mut self, metadata: &fs::Metadata) {
+        use std::os::unix::fs::MetadataExt;
+        use std::time::Duration;
+
+        self.changed = if metadata.ctime() >= 0 {
+            Some(SystemTime::UNIX_EPOCH + Duration::new(metadata.ctime() as u64, metadata.ctime_nsec() as u32))
+        } else {
+            None
+        };
+        self.mode = metadata.mode();
+        self.uid = metadata.uid();
+        self.gid = metadata.gid();
+        self.dev = metadata.dev();
+        self.inode = metadata.ino();
+        self.nlink = metadata.nlink();
+    }
+
+    #[cfg(not(unix))]
+    fn fill_unix_metadata(&mut self, metadata: &fs::Metadata) {
+        self.mode = if metadata.permissions().readonly() { 0o444 } else { 0o644 };
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct FolderStats {
     pub file_name: String,
-    pub total_size: String,
-    pub allocated_size: String,
+    pub total_size: u64,     // apparent size, like `du --apparent-size`
+    pub allocated_size: u64, // disk usage, like `du`
     pub item_count: usize,
     pub file_count: usize,
     pub subdir_count: usize,
-    pub last_modified: String,
+    pub inode_count: usize, // distinct inodes used by the subtree, hardlinks count once
+    pub shared_size: u64,   // bytes of files that have more than one hardlink, counted once
+    pub extra_links: usize, // paths skipped because their inode was already counted
+    pub excluded_size: u64, // bytes pruned by exclusion rules, not part of total_size
+    pub error_count: usize, // unreadable paths, whatever they hold is missing from the totals
+    pub last_modified: Option<SystemTime>,
 }
 
-pub fn scan_directory(path: &PathBuf) -> Vec<FileEntry> {
-    let mut files = Vec::new();
-    for entry in WalkDir::new(path).min_depth(1) {
-        if let Ok(entry) = entry {
-            let depth = entry.depth() as u32;
-            let is_folder = entry.file_type().is_dir();
-
-            let metadata = match fs::metadata(entry.path()) {
-                Ok(meta) => meta,
-                Err(_) => continue,
-            };
-
-            let file_name = entry.file_name().to_string_lossy().to_string();
-            let file_size = human_readable_size(metadata.len());
-            let modified_time = match metadata.modified() {
-                Ok(time) => human_readable_time(time),
-                Err(_) => "Unknown".to_string(),
-            };
-
-            files.push(FileEntry {
-                path: entry.path().to_path_buf(),
-                name: file_name,
-                size: file_size,
-                modified: modified_time,
-                depth,          // Assign depth
-                is_folder,      // Assign folder status
-            });
-        }
-    }
-    files
+#[derive(Debug, Clone)]
+pub struct InodeHotspot {
+    pub path: PathBuf,
+    pub entry_count: usize, // direct children of the directory
 }
 
+pub fn scan_directory(path: &Path) -> Vec<FileEntry> {
+    scan_directory_with(path, &ScanOptions::default())
+}
+
+// Every entry under `path` (the root excluded), parents before children, in `options.sort_order`
+pub fn scan_directory_with(path: &Path, options: &ScanOptions) -> Vec<FileEntry> {
+    scan_directory_report(path, options).0
+}
 
+// Same as scan_directory_with, plus every path that couldn't be read on the way
+pub fn scan_directory_report(path: &Path, options: &ScanOptions) -> (Vec<FileEntry>, ScanReport) {
+    let tree = match scan_tree(path, options, &ScanControl::new()) {
+        Some(tree) => tree,
+        None => return (Vec::new(), ScanReport::default()),
+    };
 
-pub fn compute_folder_stats(path: &PathBuf) -> Option<FolderStats> {
-    let mut total_size = 0;
-    let mut item_count = 0;
-    let mut file_count = 0;
-    let mut subdir_count = 0;
-    let mut last_modified = None;
+    let entries = tree.descendants(tree.root())
+        .into_iter()
+        .skip(1)
+        .map(|id| tree.node(id).entry.clone())
+        .collect();
+    (entries, tree.report().clone())
+}
 
-    for entry in WalkDir::new(path) {
-        if let Ok(entry) = entry {
-            let metadata = match fs::metadata(entry.path()) {
-                Ok(meta) => meta,
-                Err(_) => continue,  // Skip if unable to read
-            };
+// Both walk the subtree once through DirTree, which owns the size accounting rules
+pub fn compute_folder_stats(path: &Path) -> Option<FolderStats> {
+    let tree = DirTree::scan(path)?;
+    Some(tree.folder_stats(tree.root()))
+}
 
-            total_size += metadata.len();
-            item_count += 1;
+pub fn compute_folder_stats_with(path: &Path, options: &ScanOptions) -> Option<FolderStats> {
+    let tree = scan_tree(path, options, &ScanControl::new())?;
+    Some(tree.folder_stats(tree.root()))
+}
 
-            if entry.file_type().is_file() {
-                file_count += 1;
-            } else if entry.file_type().is_dir() {
-                subdir_count += 1;
-            }
+// Directories under `path` holding the most entries, largest first
+pub fn inode_hotspots(path: &Path, limit: usize) -> Vec<InodeHotspot> {
+    match DirTree::scan(path) {
+        Some(tree) => tree.inode_hotspots(tree.root(), limit),
+        None => Vec::new(),
+    }
+}
 
-            if let Ok(modified) = metadata.modified() {
-                if last_modified.is_none() || modified > last_modified.unwrap() {
-                    last_modified = Some(modified);
-                }
-            }
-        }
+// Metadata of a scanned path. A symlink is sized as the link itself unless links are followed;
+// a followed link that leads nowhere (dangling, looping) is still the link itself, so it stays listed.
+pub fn entry_metadata(path: &Path, follow_symlinks: bool) -> std::io::Result<fs::Metadata> {
+    let link = fs::symlink_metadata(path)?;
+    if !follow_symlinks || !link.file_type().is_symlink() {
+        return Ok(link);
     }
+    Ok(fs::metadata(path).unwrap_or(link))
+}
+
+// st_blocks is always in 512 byte units, whatever the filesystem block size is
+#[cfg(unix)]
+pub fn allocated_bytes(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.blocks() * 512
+}
 
-    let file_name = path.file_name()
-        .and_then(|name| name.to_str())
-        .unwrap_or("Unknown")
-        .to_string();
-
-    Some(FolderStats {
-        file_name,
-        total_size: human_readable_size(total_size),
-        allocated_size: human_readable_size(total_size),
-        item_count,
-        file_count,
-        subdir_count,
-        last_modified: match last_modified {
-            Some(time) => human_readable_time(time),
-            None => "Unknown".to_string(),
-        },
-    })
+#[cfg(not(unix))]
+pub fn allocated_bytes(metadata: &fs::Metadata) -> u64 {
+    metadata.len()
 }
@@ -0,0 +1,280 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use rayon::prelude::*;
+use sha1::{Digest, Sha1};
+use super::directory::FileKind;
+use super::progress::ScanControl;
+use super::report::{ScanError, ScanReport};
+use super::tree::{DirTree, NodeId};
+
+// Bytes hashed from each end of a file before committing to reading all of it
+const PARTIAL_SIZE: u64 = 4096;
+
+// Files with the same size, and once confirmed the same content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateGroup {
+    pub size: u64, // of each copy
+    pub files: Vec<PathBuf>,
+}
+
+impl DuplicateGroup {
+    // What deleting every copy but one would free
+    pub fn reclaimable(&self) -> u64 {
+        self.size * self.files.len().saturating_sub(1) as u64
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Duplicates {
+    pub groups: Vec<DuplicateGroup>, // most reclaimable first
+    pub report: ScanReport,          // files that couldn't be read, they are left out of every group
+}
+
+impl Duplicates {
+    pub fn reclaimable(&self) -> u64 {
+        self.groups.iter().map(DuplicateGroup::reclaimable).sum()
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.groups.iter().map(|group| group.files.len()).sum()
+    }
+}
+
+// Every regular file of at least `min_size` bytes under `root` grouped by size, only sizes seen more than once.
+// Paths to one inode (hardlinks, or a followed symlink and its target) are the same file, only the first is kept.
+// Followed symlinks are left out: deleting one would leave its target alone, not free anything.
+pub fn same_size_groups(tree: &DirTree, root: NodeId, min_size: u64) -> Vec<DuplicateGroup> {
+    let mut seen_inodes = HashSet::new();
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    for id in tree.descendants(root) {
+        let entry = &tree.node(id).entry;
+        if entry.is_folder || entry.file_type != FileKind::File || entry.size < min_size.max(1) {
+            continue;
+        }
+        by_size.entry(entry.size).or_default().push(entry.path.clone());
+    }
+
+    by_size
+        .into_iter()
+        .filter(|(_, files)| files.len() > 1)
+        .filter_map(|(size, files)| {
+            let files: Vec<PathBuf> = files
+                .into_iter()
+                .filter_map(|path| {
+                    // Only sizes seen twice get here, so checking each path again stays cheap
+                    let metadata = fs::symlink_metadata(&path).ok()?;
+                    if metadata.file_type().is_symlink() {
+                        return None;
+                    }
+                    match file_id(&metadata) {
+                        Some(inode) if !seen_inodes.insert(inode) => None,
+                        _ => Some(path),
+                    }
+                })
+                .collect();
+            (files.len() > 1).then_some(DuplicateGroup { size, files })
+        })
+        .collect()
+}
+
+// Narrow same-size groups down to identical content: first a hash of both ends of each file,
+// then, for whatever still matches, a hash of the whole file. None when cancelled through `control`.
+pub fn confirm_duplicates(groups: Vec<DuplicateGroup>, control: &ScanControl) -> Option<Duplicates> {
+    let mut report = ScanReport::default();
+
+    let partial = refine(groups, false, control, &mut report)?;
+    // Files no bigger than both ends together were hashed whole already
+    let (small, large): (Vec<DuplicateGroup>, Vec<DuplicateGroup>) =
+        partial.into_iter().partition(|group| group.size <= 2 * PARTIAL_SIZE);
+    let mut groups = refine(large, true, control, &mut report)?;
+    groups.extend(small);
+
+    for group in &mut groups {
+        group.files.sort();
+    }
+    groups.sort_by(|a, b| b.reclaimable().cmp(&a.reclaimable()).then_with(|| a.files.cmp(&b.files)));
+    control.finish();
+    Some(Duplicates { groups, report })
+}
+
+// The whole pipeline over the subtree under `root`
+pub fn find_duplicates(tree: &DirTree, root: NodeId, min_size: u64, control: &ScanControl) -> Option<Duplicates> {
+    confirm_duplicates(same_size_groups(tree, root, min_size), control)
+}
+
+// Split every group by content hash (of whole files or just their ends), in parallel over all of their files,
+// dropping the ones left alone
+fn refine(groups: Vec<DuplicateGroup>, whole: bool, control: &ScanControl, report: &mut ScanReport) -> Option<Vec<DuplicateGroup>> {
+    let files: Vec<(usize, u64, &PathBuf)> = groups
+        .iter()
+        .enumerate()
+        .flat_map(|(index, group)| group.files.iter().map(move |path| (index, group.size, path)))
+        .collect();
+
+    let hashed: Vec<(usize, &PathBuf, io::Result<[u8; 20]>)> = files
+        .into_par_iter()
+        .map(|(index, size, path)| {
+            if control.is_cancelled() {
+                return (index, path, Err(io::Error::from(io::ErrorKind::Interrupted)));
+            }
+            let result = if whole { full_hash(path, size, control) } else { partial_hash(path, size) };
+            control.record_entry(if whole { size } else { size.min(2 * PARTIAL_SIZE) });
+            (index, path, result)
+        })
+        .collect();
+    if control.is_cancelled() {
+        return None;
+    }
+
+    let mut by_hash: HashMap<(usize, [u8; 20]), Vec<PathBuf>> = HashMap::new();
+    for (index, path, result) in hashed {
+        match result {
+            Ok(digest) => by_hash.entry((index, digest)).or_default().push(path.clone()),
+            Err(error) => report.push(ScanError::from_io(path, &error)),
+        }
+    }
+    Some(
+        by_hash
+            .into_iter()
+            .filter(|(_, files)| files.len() > 1)
+            .map(|((index, _), files)| DuplicateGroup { size: groups[index].size, files })
+            .collect(),
+    )
+}
+
+// The first and last PARTIAL_SIZE bytes, or the whole file when it is smaller than both
+fn partial_hash(path: &Path, size: u64) -> io::Result<[u8; 20]> {
+    let mut file = open_unchanged(path, size)?;
+    let mut hasher = Sha1::new();
+    let mut buffer = vec![0; PARTIAL_SIZE.min(size) as usize];
+    file.read_exact(&mut buffer)?;
+    hasher.update(&buffer);
+    if size > PARTIAL_SIZE {
+        let tail = (size - PARTIAL_SIZE).max(PARTIAL_SIZE);
+        file.seek(SeekFrom::Start(tail))?;
+        buffer.clear();
+        file.take(PARTIAL_SIZE).read_to_end(&mut buffer)?;
+        hasher.update(&buffer);
+    }
+    Ok(hasher.finalize().into())
+}
+
+// Checks for cancellation between chunks, big files take a while
+fn full_hash(path: &Path, size: u64, control: &ScanControl) -> io::Result<[u8; 20]> {
+    let mut file = open_unchanged(path, size)?;
+    let mut hasher = Sha1::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        if control.is_cancelled() {
+            return Err(io::Error::from(io::ErrorKind::Interrupted));
+        }
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finalize().into())
+}
+
+#[cfg(unix)]
+fn file_id(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+// No stable inode numbers to compare
+#[cfg(not(unix))]
+fn file_id(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+// Hash what the scan saw: a file that changed size since then is no longer a candidate
+fn open_unchanged(path: &Path, size: u64) -> io::Result<File> {
+    let file = File::open(path)?;
+    if file.metadata()?.len() != size {
+        return Err(io::Error::other("file changed size since the scan"));
+    }
+    Ok(file)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::fs;
+    use std::os::unix::fs::symlink;
+    use std::path::PathBuf;
+    use super::super::options::{scan_tree, ScanOptions};
+    use super::super::progress::ScanControl;
+    use super::super::testdir::TestDir;
+    use super::{confirm_duplicates, find_duplicates, same_size_groups, PARTIAL_SIZE};
+
+    const BIG: usize = 3 * PARTIAL_SIZE as usize;
+
+    fn fixture() -> TestDir {
+        let dir = TestDir::new("duplicates");
+        // Same content, found through both hashing stages
+        dir.file("big/one", BIG, 1);
+        dir.file("big/two", BIG, 1);
+        // Same ends, only the whole-file hash tells it apart
+        let mut middle: Vec<u8> = (0..BIG).map(|i| (i % 251) as u8 ^ 1).collect();
+        middle[BIG / 2] ^= 0xff;
+        dir.write("big/middle", &middle);
+        // Same size, different start: dropped by the partial hash
+        dir.file("big/other", BIG, 2);
+        // Small enough that the partial hash covers all of it
+        dir.file("small/a", 100, 3);
+        dir.file("small/b", 100, 3);
+        // Another name for big/one and a link to it are not copies
+        fs::hard_link(dir.path().join("big/one"), dir.path().join("big/one-again")).unwrap();
+        symlink(dir.path().join("big/two"), dir.path().join("two-link")).unwrap();
+        dir
+    }
+
+    fn groups(dir: &TestDir, follow_symlinks: bool) -> Vec<Vec<PathBuf>> {
+        let tree = scan_tree(dir.path(), &ScanOptions::new().follow_symlinks(follow_symlinks), &ScanControl::new()).unwrap();
+        let duplicates = find_duplicates(&tree, tree.root(), 1, &ScanControl::new()).unwrap();
+        assert!(duplicates.report.errors.is_empty());
+        duplicates
+            .groups
+            .into_iter()
+            .map(|group| group.files.iter().map(|path| path.strip_prefix(dir.path()).unwrap().to_path_buf()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn only_identical_content_is_grouped() {
+        let dir = fixture();
+        for follow_symlinks in [false, true] {
+            let found = groups(&dir, follow_symlinks);
+            assert_eq!(found.len(), 2, "follow_symlinks = {}", follow_symlinks);
+            // Most reclaimable first, and exactly one of the hardlinked names
+            assert_eq!(found[0].len(), 2);
+            assert!(found[0].contains(&PathBuf::from("big/two")));
+            assert!(found[0].contains(&PathBuf::from("big/one")) ^ found[0].contains(&PathBuf::from("big/one-again")));
+            assert_eq!(found[1], vec![PathBuf::from("small/a"), PathBuf::from("small/b")]);
+        }
+    }
+
+    #[test]
+    fn candidates_skip_links_and_small_files() {
+        let dir = fixture();
+        let tree = scan_tree(dir.path(), &ScanOptions::new().follow_symlinks(true), &ScanControl::new()).unwrap();
+        let candidates = same_size_groups(&tree, tree.root(), 1000);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].size, BIG as u64);
+        // one or one-again, two, middle and other; never two-link
+        assert_eq!(candidates[0].files.len(), 4);
+        assert!(!candidates[0].files.contains(&dir.path().join("two-link")));
+    }
+
+    #[test]
+    fn cancelling_gives_up() {
+        let dir = fixture();
+        let tree = scan_tree(dir.path(), &ScanOptions::new(), &ScanControl::new()).unwrap();
+        let control = ScanControl::new();
+        control.cancel();
+        assert!(confirm_duplicates(same_size_groups(&tree, tree.root(), 1), &control).is_none());
+    }
+}
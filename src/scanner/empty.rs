@@ -0,0 +1,106 @@
+use std::path::PathBuf;
+use super::directory::FileKind;
+use super::tree::{DirTree, NodeId};
+
+// Leftovers of build tools and extractions: folders with nothing but other empty folders in them,
+// and files with no content at all
+#[derive(Debug, Clone, Default)]
+pub struct EmptyEntries {
+    pub folders: Vec<PathBuf>, // only the outermost empty folder of each empty subtree
+    pub files: Vec<PathBuf>,
+}
+
+impl EmptyEntries {
+    pub fn is_empty(&self) -> bool {
+        self.folders.is_empty() && self.files.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.folders.len() + self.files.len()
+    }
+}
+
+// Everything empty below `root`, `root` itself never included.
+// A folder only counts as empty when the scan saw all of it: nothing excluded, nothing unreadable.
+// Folders at the max_depth limit keep the counts of what is under them, so they are judged the same way.
+pub fn find_empty(tree: &DirTree, root: NodeId) -> EmptyEntries {
+    let mut empty = EmptyEntries::default();
+    let mut stack: Vec<NodeId> = tree.children(root).iter().rev().copied().collect();
+    while let Some(id) = stack.pop() {
+        let node = tree.node(id);
+        if node.entry.is_folder {
+            // subdir_count includes the folder itself, item_count doesn't
+            let recursively_empty = node.item_count + 1 == node.subdir_count && node.excluded_size == 0 && node.error_count == 0;
+            if recursively_empty {
+                // Its empty subfolders go with it
+                empty.folders.push(node.entry.path.clone());
+            } else {
+                stack.extend(tree.children(id).iter().rev());
+            }
+        } else if node.entry.file_type == FileKind::File && node.entry.size == 0 {
+            empty.files.push(node.entry.path.clone());
+        }
+    }
+    empty
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::fs;
+    use std::os::unix::fs::{symlink, PermissionsExt};
+    use std::path::PathBuf;
+    use super::super::options::{scan_tree, ScanOptions};
+    use super::super::progress::ScanControl;
+    use super::super::testdir::TestDir;
+    use super::find_empty;
+
+    #[test]
+    fn nested_empty_folders_are_one_item() {
+        let dir = TestDir::new("empty");
+        dir.dir("build/cache/objects/deep");
+        dir.dir("build/tmp");
+        dir.dir("src/empty");
+        dir.file("src/main.rs", 100, 1);
+        dir.write("src/zero", b"");
+        // Not a regular file, however small
+        symlink("main.rs", dir.path().join("src/link")).unwrap();
+
+        let tree = scan_tree(dir.path(), &ScanOptions::new(), &ScanControl::new()).unwrap();
+        let empty = find_empty(&tree, tree.root());
+        // In path order, the walk goes by the tree's sort order
+        let relative = |paths: &[PathBuf]| -> Vec<String> {
+            let mut paths: Vec<String> = paths.iter().map(|path| path.strip_prefix(dir.path()).unwrap().to_string_lossy().to_string()).collect();
+            paths.sort();
+            paths
+        };
+        assert_eq!(relative(&empty.folders), ["build", "src/empty"]);
+        assert_eq!(relative(&empty.files), ["src/zero"]);
+        assert_eq!(empty.len(), 3);
+
+        // Below an empty folder there is nothing else to find
+        let build = tree.find(&dir.path().join("build")).unwrap();
+        assert_eq!(relative(&find_empty(&tree, build).folders), ["build/cache", "build/tmp"]);
+    }
+
+    #[test]
+    fn folders_the_scan_didnt_fully_see_are_not_empty() {
+        let dir = TestDir::new("empty-partial");
+        dir.file("excluded/debug.log", 500, 1);
+        dir.dir("looping/inner");
+        symlink("..", dir.path().join("looping/inner/up")).unwrap();
+        let locked = dir.dir("locked");
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+
+        let options = ScanOptions::new().exclude(["*.log"]).follow_symlinks(true);
+        let tree = scan_tree(dir.path(), &options, &ScanControl::new()).unwrap();
+        let empty = find_empty(&tree, tree.root());
+        let unreadable = fs::read_dir(&locked).is_err();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert!(!empty.folders.contains(&dir.path().join("excluded")));
+        assert!(!empty.folders.iter().any(|path| path.starts_with(dir.path().join("looping"))));
+        // Root reads it anyway, and then it really is empty
+        assert_eq!(empty.folders.contains(&locked), !unreadable);
+        assert!(empty.folders.iter().all(|path| *path == locked));
+    }
+}
@@ -0,0 +1,208 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use walkdir::WalkDir;
+use super::options::ScanOptions;
+use super::progress::ScanControl;
+
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+// Compiled exclude/include patterns for one scan root. Patterns use .gitignore syntax,
+// so `node_modules` matches at any depth while `/build` or `.git/objects` are anchored to the root.
+#[derive(Debug, Clone)]
+pub struct ExcludeRules {
+    exclude: Gitignore,
+    include: Gitignore, // when not empty, only files matching one of these are kept
+    use_ignore_files: bool,
+}
+
+// .gitignore / .ignore files found on the way down, innermost last
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreStack(Vec<Arc<Gitignore>>);
+
+impl ExcludeRules {
+    pub fn new(root: &Path, options: &ScanOptions) -> ExcludeRules {
+        ExcludeRules {
+            exclude: build_patterns(root, &options.exclude),
+            include: build_patterns(root, &options.include),
+            use_ignore_files: options.use_ignore_files,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.exclude.is_empty() || !self.include.is_empty() || self.use_ignore_files
+    }
+
+    // Ignore files of `dir` stacked on top of the ones inherited from its parents
+    pub fn enter(&self, dir: &Path, inherited: &IgnoreStack) -> IgnoreStack {
+        if !self.use_ignore_files {
+            return IgnoreStack::default();
+        }
+
+        let mut stack = inherited.clone();
+        for name in IGNORE_FILES {
+            let file = dir.join(name);
+            if file.is_file() {
+                let (gitignore, _error) = Gitignore::new(&file);
+                if !gitignore.is_empty() {
+                    stack.0.push(Arc::new(gitignore));
+                }
+            }
+        }
+        stack
+    }
+
+    // `stack` is the one of the directory containing `path`
+    pub fn is_excluded(&self, path: &Path, is_dir: bool, stack: &IgnoreStack) -> bool {
+        if self.exclude.matched(path, is_dir).is_ignore() {
+            return true;
+        }
+
+        // The innermost ignore file with an opinion wins, `!pattern` lines re-include
+        for gitignore in stack.0.iter().rev() {
+            match gitignore.matched(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => break,
+                Match::None => {}
+            }
+        }
+
+        // Directories are always entered so the files inside them get a chance to match
+        !is_dir && !self.include.is_empty() && !self.include.matched(path, false).is_ignore()
+    }
+}
+
+fn build_patterns(root: &Path, patterns: &[String]) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(root);
+    for pattern in patterns.iter().map(|pattern| pattern.trim()).filter(|pattern| !pattern.is_empty()) {
+        // A bad pattern only drops that pattern, not the whole scan
+        let _ = builder.add_line(None, pattern);
+    }
+    builder.build().unwrap_or_else(|_| Gitignore::empty())
+}
+
+// Apparent size of something that was pruned from the scan, so it can still be reported.
+// With `root_dev` (one_file_system) other filesystems mounted inside it are left out, like the walkers leave them out,
+// and a cancelled scan stops sizing right away.
+pub fn excluded_size(path: &Path, is_dir: bool, root_dev: Option<u64>, control: &ScanControl) -> u64 {
+    if !is_dir {
+        return fs::symlink_metadata(path).map(|meta| meta.len()).unwrap_or(0);
+    }
+
+    let mut total = 0;
+    let mut walker = WalkDir::new(path).into_iter();
+    while let Some(entry) = walker.next() {
+        if control.is_cancelled() {
+            break;
+        }
+        let Some(meta) = entry.ok().and_then(|entry| entry.metadata().ok()) else {
+            continue;
+        };
+        if root_dev.is_some_and(|dev| device(&meta) != dev) {
+            if meta.is_dir() {
+                walker.skip_current_dir();
+            }
+            continue;
+        }
+        total += meta.len();
+    }
+    total
+}
+
+#[cfg(unix)]
+fn device(meta: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    meta.dev()
+}
+
+// FileEntry::dev is 0 here too, so nothing ever looks like another filesystem
+#[cfg(not(unix))]
+fn device(_meta: &fs::Metadata) -> u64 {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use super::super::options::{scan_tree, ScanOptions};
+    use super::super::progress::ScanControl;
+    use super::super::testdir::TestDir;
+    use super::{excluded_size, ExcludeRules, IgnoreStack};
+
+    #[test]
+    fn exclude_patterns_follow_gitignore_rules() {
+        let root = Path::new("/project");
+        let rules = ExcludeRules::new(root, &ScanOptions::new().exclude(["node_modules", "/build", "*.log", " "]));
+        let none = IgnoreStack::default();
+        assert!(rules.is_active());
+
+        // Unanchored names match at any depth, a leading slash anchors to the root
+        assert!(rules.is_excluded(&root.join("node_modules"), true, &none));
+        assert!(rules.is_excluded(&root.join("web/app/node_modules"), true, &none));
+        assert!(rules.is_excluded(&root.join("build"), true, &none));
+        assert!(!rules.is_excluded(&root.join("src/build"), true, &none));
+        assert!(rules.is_excluded(&root.join("src/debug.log"), false, &none));
+        assert!(!rules.is_excluded(&root.join("src/main.rs"), false, &none));
+    }
+
+    #[test]
+    fn include_patterns_only_filter_files() {
+        let root = Path::new("/project");
+        let rules = ExcludeRules::new(root, &ScanOptions::new().include(["*.rs"]));
+        let none = IgnoreStack::default();
+        assert!(!rules.is_excluded(&root.join("src/main.rs"), false, &none));
+        assert!(rules.is_excluded(&root.join("README.md"), false, &none));
+        // Folders are still entered, files further down may match
+        assert!(!rules.is_excluded(&root.join("docs"), true, &none));
+
+        assert!(!ExcludeRules::new(root, &ScanOptions::new()).is_active());
+    }
+
+    #[test]
+    fn ignore_files_stack_and_reinclude() {
+        let dir = TestDir::new("ignore-files");
+        dir.write(".gitignore", b"*.tmp\n!keep.tmp\n");
+        dir.write("sub/.ignore", b"data/\n");
+        let sub = dir.path().join("sub");
+
+        let rules = ExcludeRules::new(dir.path(), &ScanOptions::new().use_ignore_files(true));
+        let top = rules.enter(dir.path(), &IgnoreStack::default());
+        let inner = rules.enter(&sub, &top);
+
+        assert!(rules.is_excluded(&dir.path().join("scratch.tmp"), false, &top));
+        assert!(!rules.is_excluded(&dir.path().join("keep.tmp"), false, &top));
+        assert!(rules.is_excluded(&sub.join("more.tmp"), false, &inner));
+        assert!(rules.is_excluded(&sub.join("data"), true, &inner));
+        // sub/.ignore only applies inside sub
+        assert!(!rules.is_excluded(&dir.path().join("data"), true, &top));
+
+        // Without use_ignore_files the files are never read
+        let plain = ExcludeRules::new(dir.path(), &ScanOptions::new());
+        let stack = plain.enter(dir.path(), &IgnoreStack::default());
+        assert!(!plain.is_excluded(&dir.path().join("scratch.tmp"), false, &stack));
+    }
+
+    #[test]
+    fn excluded_subtrees_are_sized_not_scanned() {
+        let dir = TestDir::new("excluded-size");
+        dir.file("keep/a", 1000, 1);
+        dir.file("node_modules/pkg/index.js", 7000, 2);
+        dir.file("node_modules/pkg/lib/b.js", 3000, 3);
+
+        for threads in [1, 4] {
+            let options = ScanOptions::new().threads(threads).exclude(["node_modules"]);
+            let tree = scan_tree(dir.path(), &options, &ScanControl::new()).unwrap();
+            let root = tree.node(tree.root());
+            assert!(tree.find(&dir.path().join("node_modules")).is_none());
+            assert!(root.excluded_size >= 10_000, "threads = {}", threads);
+            assert!(root.total_size < 10_000);
+        }
+
+        let cancelled = ScanControl::new();
+        cancelled.cancel();
+        assert_eq!(excluded_size(&dir.path().join("node_modules"), true, None, &cancelled), 0);
+        assert_eq!(excluded_size(&dir.path().join("keep/a"), false, None, &ScanControl::new()), 1000);
+    }
+}
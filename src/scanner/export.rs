@@ -0,0 +1,464 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::SystemTime;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use super::directory::{FileEntry, FileKind, FolderStats};
+use super::stats::DriveUsage;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    #[default]
+    Json,
+    Csv,
+    Ndjson, // one JSON object per line, written as the entries come
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [ExportFormat::Json, ExportFormat::Csv, ExportFormat::Ndjson];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+        }
+    }
+
+    // "json", "csv", "ndjson" (or "jsonl"), case insensitive
+    pub fn from_name(name: &str) -> Option<ExportFormat> {
+        match name.to_ascii_lowercase().as_str() {
+            "json" => Some(ExportFormat::Json),
+            "csv" => Some(ExportFormat::Csv),
+            "ndjson" | "jsonl" => Some(ExportFormat::Ndjson),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<ExportFormat> {
+        ExportFormat::from_name(&path.extension()?.to_string_lossy())
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ExportFormat::Json => "JSON",
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Ndjson => "NDJSON",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Flat records so every format gets the same columns; times are RFC 3339 in UTC
+#[derive(Serialize)]
+struct EntryRecord<'a> {
+    path: String,
+    name: &'a str,
+    kind: &'static str,
+    size: u64,
+    allocated_size: u64,
+    modified: Option<String>,
+    accessed: Option<String>,
+    changed: Option<String>,
+    mode: u32,
+    uid: u32,
+    gid: u32,
+    dev: u64,
+    inode: u64,
+    nlink: u64,
+    depth: u32,
+}
+
+#[derive(Serialize)]
+struct FolderStatsRecord<'a> {
+    name: &'a str,
+    total_size: u64,
+    allocated_size: u64,
+    item_count: usize,
+    file_count: usize,
+    subdir_count: usize,
+    inode_count: usize,
+    shared_size: u64,
+    extra_links: usize,
+    excluded_size: u64,
+    error_count: usize,
+    last_modified: Option<String>,
+}
+
+#[derive(Serialize)]
+struct DriveUsageRecord {
+    mount_point: String,
+    total_space: u64,
+    used_space: u64,
+    free_space: u64,
+    available_space: u64,
+    reserved_space: u64,
+    used_percentage: f64,
+    total_inodes: u64,
+    used_inodes: u64,
+    free_inodes: u64,
+}
+
+impl<'a> From<&'a FileEntry> for EntryRecord<'a> {
+    fn from(entry: &'a FileEntry) -> Self {
+        EntryRecord {
+            path: entry.path.to_string_lossy().to_string(),
+            name: &entry.name,
+            kind: kind_name(entry.file_type),
+            size: entry.size,
+            allocated_size: entry.allocated_size,
+            modified: entry.modified.map(rfc3339),
+            accessed: entry.accessed.map(rfc3339),
+            changed: entry.changed.map(rfc3339),
+            mode: entry.mode,
+            uid: entry.uid,
+            gid: entry.gid,
+            dev: entry.dev,
+            inode: entry.inode,
+            nlink: entry.nlink,
+            depth: entry.depth,
+        }
+    }
+}
+
+impl<'a> From<&'a FolderStats> for FolderStatsRecord<'a> {
+    fn from(stats: &'a FolderStats) -> Self {
+        FolderStatsRecord {
+            name: &stats.file_name,
+            total_size: stats.total_size,
+            allocated_size: stats.allocated_size,
+            item_count: stats.item_count,
+            file_count: stats.file_count,
+            subdir_count: stats.subdir_count,
+            inode_count: stats.inode_count,
+            shared_size: stats.shared_size,
+            extra_links: stats.extra_links,
+            excluded_size: stats.excluded_size,
+            error_count: stats.error_count,
+            last_modified: stats.last_modified.map(rfc3339),
+        }
+    }
+}
+
+impl From<&DriveUsage> for DriveUsageRecord {
+    fn from(usage: &DriveUsage) -> Self {
+        DriveUsageRecord {
+            mount_point: usage.mount_point.to_string_lossy().to_string(),
+            total_space: usage.total_space,
+            used_space: usage.used_space(),
+            free_space: usage.free_space,
+            available_space: usage.available_space,
+            reserved_space: usage.reserved_space,
+            used_percentage: usage.used_percentage(),
+            total_inodes: usage.total_inodes,
+            used_inodes: usage.used_inodes(),
+            free_inodes: usage.free_inodes,
+        }
+    }
+}
+
+// Entries are written one at a time, so a whole scan never has to be held as text
+pub fn write_entries<'a, W, I>(writer: W, entries: I, format: ExportFormat) -> io::Result<()>
+where
+    W: Write,
+    I: IntoIterator<Item = &'a FileEntry>,
+{
+    write_records(writer, entries.into_iter().map(EntryRecord::from), format)
+}
+
+pub fn write_folder_stats<W: Write>(writer: W, stats: &FolderStats, format: ExportFormat) -> io::Result<()> {
+    match format {
+        // A single object rather than a one element array
+        ExportFormat::Json => write_json(writer, &FolderStatsRecord::from(stats)),
+        _ => write_records(writer, std::iter::once(FolderStatsRecord::from(stats)), format),
+    }
+}
+
+pub fn write_drive_usage<'a, W, I>(writer: W, usages: I, format: ExportFormat) -> io::Result<()>
+where
+    W: Write,
+    I: IntoIterator<Item = &'a DriveUsage>,
+{
+    write_records(writer, usages.into_iter().map(DriveUsageRecord::from), format)
+}
+
+// Folder totals, drive usage and every entry in one file, what the GUI's Export button writes.
+// CSV can only hold one table, so it gets the entries alone.
+pub fn write_scan<'a, W, I>(
+    mut writer: W,
+    stats: &FolderStats,
+    usage: Option<&DriveUsage>,
+    entries: I,
+    format: ExportFormat,
+) -> io::Result<()>
+where
+    W: Write,
+    I: IntoIterator<Item = &'a FileEntry>,
+{
+    match format {
+        ExportFormat::Json => {
+            writer.write_all(b"{\n\"folder\": ")?;
+            serde_json::to_writer(&mut writer, &FolderStatsRecord::from(stats))?;
+            writer.write_all(b",\n\"drive_usage\": ")?;
+            serde_json::to_writer(&mut writer, &usage.map(DriveUsageRecord::from))?;
+            writer.write_all(b",\n\"entries\": ")?;
+            write_entries(&mut writer, entries, format)?;
+            writer.write_all(b"}\n")?;
+            writer.flush()
+        }
+        ExportFormat::Ndjson => {
+            // Every line says what it holds, so scripts can filter on "record"
+            write_tagged(&mut writer, "folder", FolderStatsRecord::from(stats))?;
+            if let Some(usage) = usage {
+                write_tagged(&mut writer, "drive_usage", DriveUsageRecord::from(usage))?;
+            }
+            for entry in entries {
+                write_tagged(&mut writer, "entry", EntryRecord::from(entry))?;
+            }
+            writer.flush()
+        }
+        ExportFormat::Csv => write_entries(writer, entries, format),
+    }
+}
+
+#[derive(Serialize)]
+struct Tagged<T> {
+    record: &'static str,
+    #[serde(flatten)]
+    data: T,
+}
+
+fn write_tagged<W: Write, T: Serialize>(mut writer: W, record: &'static str, data: T) -> io::Result<()> {
+    serde_json::to_writer(&mut writer, &Tagged { record, data })?;
+    writer.write_all(b"\n")
+}
+
+fn write_records<W, T, I>(mut writer: W, records: I, format: ExportFormat) -> io::Result<()>
+where
+    W: Write,
+    T: Serialize,
+    I: Iterator<Item = T>,
+{
+    match format {
+        ExportFormat::Json => {
+            writer.write_all(b"[")?;
+            for (i, record) in records.enumerate() {
+                writer.write_all(if i == 0 { b"\n  " } else { b",\n  " })?;
+                serde_json::to_writer(&mut writer, &record)?;
+            }
+            writer.write_all(b"\n]\n")?;
+        }
+        ExportFormat::Ndjson => {
+            for record in records {
+                serde_json::to_writer(&mut writer, &record)?;
+                writer.write_all(b"\n")?;
+            }
+        }
+        ExportFormat::Csv => {
+            let mut csv = csv::Writer::from_writer(&mut writer);
+            for record in records {
+                csv.serialize(record)?;
+            }
+            csv.flush()?;
+        }
+    }
+    writer.flush()
+}
+
+fn write_json<W: Write, T: Serialize>(mut writer: W, value: &T) -> io::Result<()> {
+    serde_json::to_writer_pretty(&mut writer, value)?;
+    writer.write_all(b"\n")?;
+    writer.flush()
+}
+
+fn kind_name(kind: FileKind) -> &'static str {
+    match kind {
+        FileKind::File => "file",
+        FileKind::Directory => "directory",
+        FileKind::Symlink => "symlink",
+        FileKind::Other => "other",
+    }
+}
+
+fn rfc3339(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).to_rfc3339()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use serde_json::Value;
+    use super::super::directory::FileEntry;
+    use super::super::options::{scan_tree, ScanOptions};
+    use super::super::progress::ScanControl;
+    use super::super::stats::DriveUsage;
+    use super::super::testdir::TestDir;
+    use super::super::tree::DirTree;
+    use super::{write_drive_usage, write_entries, write_folder_stats, write_scan, ExportFormat};
+
+    const ENTRY_COLUMNS: [&str; 15] = [
+        "path", "name", "kind", "size", "allocated_size", "modified", "accessed", "changed",
+        "mode", "uid", "gid", "dev", "inode", "nlink", "depth",
+    ];
+
+    // A name that needs quoting in CSV and escaping in JSON
+    const AWKWARD: &str = "a, \"quoted\"\nname.txt";
+
+    fn scan() -> (TestDir, DirTree) {
+        let dir = TestDir::new("export");
+        dir.file("docs/report.pdf", 2000, 1);
+        dir.file(AWKWARD, 30, 2);
+        let tree = scan_tree(dir.path(), &ScanOptions::new(), &ScanControl::new()).unwrap();
+        (dir, tree)
+    }
+
+    fn entries(tree: &DirTree) -> Vec<&FileEntry> {
+        tree.descendants(tree.root()).into_iter().map(|id| &tree.node(id).entry).collect()
+    }
+
+    fn usage() -> DriveUsage {
+        DriveUsage {
+            mount_point: PathBuf::from("/"),
+            total_space: 1000,
+            free_space: 400,
+            available_space: 300,
+            reserved_space: 100,
+            total_inodes: 50,
+            free_inodes: 20,
+        }
+    }
+
+    fn export(format: ExportFormat, write: impl FnOnce(&mut Vec<u8>, ExportFormat) -> std::io::Result<()>) -> String {
+        let mut out = Vec::new();
+        write(&mut out, format).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    // An exported entry next to the entry it came from
+    fn check_entry(record: &Value, entry: &FileEntry) {
+        assert_eq!(record["path"], entry.path.to_string_lossy().as_ref());
+        assert_eq!(record["name"], entry.name.as_str());
+        assert_eq!(record["size"], entry.size);
+        assert_eq!(record["inode"], entry.inode);
+        assert_eq!(record["kind"], if entry.is_folder { "directory" } else { "file" });
+        assert!(record["modified"].as_str().is_some_and(|time| chrono::DateTime::parse_from_rfc3339(time).is_ok()));
+    }
+
+    #[test]
+    fn json_entries_parse_back() {
+        let (_dir, tree) = scan();
+        let entries = entries(&tree);
+        let json = export(ExportFormat::Json, |out, format| write_entries(out, entries.iter().copied(), format));
+        let records: Vec<Value> = serde_json::from_str(&json).unwrap();
+        assert_eq!(records.len(), entries.len());
+        for (record, entry) in records.iter().zip(&entries) {
+            let keys: Vec<&str> = record.as_object().unwrap().keys().map(String::as_str).collect();
+            assert_eq!(keys.len(), ENTRY_COLUMNS.len());
+            assert!(ENTRY_COLUMNS.iter().all(|column| keys.contains(column)));
+            check_entry(record, entry);
+        }
+        assert!(records.iter().any(|record| record["name"] == AWKWARD));
+
+        let empty = export(ExportFormat::Json, |out, format| write_entries(out, [], format));
+        assert_eq!(serde_json::from_str::<Vec<Value>>(&empty).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn ndjson_entries_are_one_object_per_line() {
+        let (_dir, tree) = scan();
+        let entries = entries(&tree);
+        let ndjson = export(ExportFormat::Ndjson, |out, format| write_entries(out, entries.iter().copied(), format));
+        let lines: Vec<&str> = ndjson.lines().collect();
+        assert_eq!(lines.len(), entries.len());
+        for (line, entry) in lines.iter().zip(&entries) {
+            check_entry(&serde_json::from_str(line).unwrap(), entry);
+        }
+    }
+
+    #[test]
+    fn csv_entries_have_a_header_and_a_row_each() {
+        let (_dir, tree) = scan();
+        let entries = entries(&tree);
+        let csv = export(ExportFormat::Csv, |out, format| write_entries(out, entries.iter().copied(), format));
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+        assert_eq!(reader.headers().unwrap().iter().collect::<Vec<_>>(), ENTRY_COLUMNS);
+
+        let rows: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+        assert_eq!(rows.len(), entries.len());
+        for (row, entry) in rows.iter().zip(&entries) {
+            assert_eq!(&row[0], entry.path.to_string_lossy());
+            assert_eq!(&row[1], entry.name);
+            assert_eq!(row[3].parse::<u64>().unwrap(), entry.size);
+        }
+        assert!(rows.iter().any(|row| &row[1] == AWKWARD));
+    }
+
+    #[test]
+    fn scans_export_totals_usage_and_entries() {
+        let (_dir, tree) = scan();
+        let entries = entries(&tree);
+        let stats = tree.folder_stats(tree.root());
+        let usage = usage();
+
+        let json = export(ExportFormat::Json, |out, format| write_scan(out, &stats, Some(&usage), entries.iter().copied(), format));
+        let scan: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(scan["folder"]["total_size"], stats.total_size);
+        assert_eq!(scan["folder"]["item_count"], stats.item_count);
+        assert_eq!(scan["drive_usage"]["used_space"], 600);
+        assert_eq!(scan["entries"].as_array().unwrap().len(), entries.len());
+        let without_usage = export(ExportFormat::Json, |out, format| write_scan(out, &stats, None, [], format));
+        assert!(serde_json::from_str::<Value>(&without_usage).unwrap()["drive_usage"].is_null());
+
+        let ndjson = export(ExportFormat::Ndjson, |out, format| write_scan(out, &stats, Some(&usage), entries.iter().copied(), format));
+        let records: Vec<Value> = ndjson.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(records[0]["record"], "folder");
+        assert_eq!(records[0]["total_size"], stats.total_size);
+        assert_eq!(records[1]["record"], "drive_usage");
+        assert_eq!(records.len(), entries.len() + 2);
+        for (record, entry) in records[2..].iter().zip(&entries) {
+            assert_eq!(record["record"], "entry");
+            check_entry(record, entry);
+        }
+
+        // One table only: the entries
+        let csv = export(ExportFormat::Csv, |out, format| write_scan(out, &stats, Some(&usage), entries.iter().copied(), format));
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+        assert_eq!(reader.headers().unwrap().iter().collect::<Vec<_>>(), ENTRY_COLUMNS);
+        assert_eq!(reader.records().count(), entries.len());
+    }
+
+    #[test]
+    fn folder_stats_and_drive_usage_parse_back() {
+        let (_dir, tree) = scan();
+        let stats = tree.folder_stats(tree.root());
+        let usage = usage();
+
+        // A single object, not an array
+        let json = export(ExportFormat::Json, |out, format| write_folder_stats(out, &stats, format));
+        let record: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(record["name"], stats.file_name.as_str());
+        assert_eq!(record["file_count"], stats.file_count);
+
+        let csv = export(ExportFormat::Csv, |out, format| write_folder_stats(out, &stats, format));
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+        assert_eq!(&reader.headers().unwrap()[1], "total_size");
+        let rows: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0][1].parse::<u64>().unwrap(), stats.total_size);
+
+        for format in [ExportFormat::Json, ExportFormat::Ndjson] {
+            let out = export(format, |out, format| write_drive_usage(out, [&usage, &usage], format));
+            let records: Vec<Value> = match format {
+                ExportFormat::Json => serde_json::from_str(&out).unwrap(),
+                _ => out.lines().map(|line| serde_json::from_str(line).unwrap()).collect(),
+            };
+            assert_eq!(records.len(), 2);
+            assert_eq!(records[0]["mount_point"], "/");
+            assert_eq!(records[0]["used_inodes"], 30);
+            assert_eq!(records[0]["used_percentage"], 60.0);
+        }
+    }
+}
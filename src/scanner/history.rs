@@ -0,0 +1,351 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use super::tree::DirTree;
+
+// Points kept per folder, older ones are dropped when the file is compacted
+const MAX_POINTS: usize = 100;
+
+// Folder totals of every scan, appended to a plain NDJSON file: one line per recorded folder per scan.
+// The scan root and its subfolders are recorded every time, deeper folders only when they changed,
+// so a tree that sits still barely grows the file. Appends never rewrite lines; compact() does, through a temp file.
+#[derive(Debug, Clone)]
+pub struct History {
+    file: PathBuf,
+    lock: Arc<Mutex<()>>, // clones write from other threads, one at a time
+}
+
+// One folder's totals at one scan
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistoryPoint {
+    pub scanned_at: SystemTime,
+    pub size: u64,
+    pub allocated_size: u64,
+    pub item_count: usize,
+}
+
+// Everything in the history file, grouped by folder, oldest point first
+#[derive(Debug, Clone, Default)]
+pub struct HistoryIndex {
+    by_path: HashMap<PathBuf, Vec<HistoryPoint>>,
+    dropped: usize, // lines of the file that were unreadable or past MAX_POINTS
+}
+
+#[derive(Serialize, Deserialize)]
+struct HistoryLine {
+    path: PathBuf,
+    scanned_at: u64, // seconds since the unix epoch
+    size: u64,
+    allocated_size: u64,
+    item_count: usize,
+}
+
+impl History {
+    pub fn open(file: &Path) -> History {
+        History { file: file.to_path_buf(), lock: Arc::default() }
+    }
+
+    // $DISKSCANNER_HISTORY, else history.ndjson in the per-user data directory
+    pub fn default_location() -> Option<PathBuf> {
+        if let Some(file) = std::env::var_os("DISKSCANNER_HISTORY") {
+            return Some(PathBuf::from(file));
+        }
+        let data_dir = if cfg!(windows) {
+            PathBuf::from(std::env::var_os("APPDATA")?)
+        } else if let Some(data_home) = std::env::var_os("XDG_DATA_HOME") {
+            PathBuf::from(data_home)
+        } else {
+            PathBuf::from(std::env::var_os("HOME")?).join(".local").join("share")
+        };
+        Some(data_dir.join("diskscanner").join("history.ndjson"))
+    }
+
+    pub fn file(&self) -> &Path {
+        &self.file
+    }
+
+    // Append what `tree` adds to `index` (see HistoryIndex::scan_points) and add it to `index` too;
+    // returns how many folders were written
+    pub fn record(&self, tree: &DirTree, scanned_at: SystemTime, index: &mut HistoryIndex) -> io::Result<usize> {
+        let points = index.scan_points(tree, scanned_at);
+        self.append(&points)?;
+        index.add_points(points.iter().cloned());
+        Ok(points.len())
+    }
+
+    // One write per call, so appends from two threads don't interleave their lines
+    pub fn append(&self, points: &[(PathBuf, HistoryPoint)]) -> io::Result<()> {
+        if points.is_empty() {
+            return Ok(());
+        }
+        let mut buffer = Vec::new();
+        for (path, point) in points {
+            serde_json::to_writer(&mut buffer, &HistoryLine::new(path, point))?;
+            buffer.push(b'\n');
+        }
+
+        let _guard = self.lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(dir) = self.file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.file)?;
+        file.write_all(&buffer)
+    }
+
+    // Rewrite the file with just what `index` holds, when loading it dropped anything
+    pub fn compact(&self, index: &HistoryIndex) -> io::Result<()> {
+        if index.dropped == 0 {
+            return Ok(());
+        }
+        let _guard = self.lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let temporary = self.file.with_extension("ndjson.tmp");
+        let mut writer = BufWriter::new(File::create(&temporary)?);
+        let written = index
+            .by_path
+            .iter()
+            .flat_map(|(path, points)| points.iter().map(move |point| HistoryLine::new(path, point)))
+            .try_for_each(|line| {
+                serde_json::to_writer(&mut writer, &line)?;
+                writer.write_all(b"\n")
+            })
+            .and_then(|_| writer.flush());
+        drop(writer);
+        if let Err(e) = written.and_then(|_| fs::rename(&temporary, &self.file)) {
+            let _ = fs::remove_file(&temporary);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    // Read the whole file; a missing file is just an empty history, unreadable lines are skipped
+    pub fn load(&self) -> io::Result<HistoryIndex> {
+        let file = match File::open(&self.file) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HistoryIndex::default()),
+            Err(e) => return Err(e),
+        };
+
+        let mut index = HistoryIndex::default();
+        for line in BufReader::new(file).lines() {
+            let Ok(line) = serde_json::from_str::<HistoryLine>(&line?) else {
+                index.dropped += 1;
+                continue;
+            };
+            index.insert(line.path, HistoryPoint {
+                scanned_at: UNIX_EPOCH + Duration::from_secs(line.scanned_at),
+                size: line.size,
+                allocated_size: line.allocated_size,
+                item_count: line.item_count,
+            });
+        }
+        Ok(index)
+    }
+
+    // load() then compact(), what a long running front end does once at startup
+    pub fn load_compacted(&self) -> io::Result<HistoryIndex> {
+        let index = self.load()?;
+        self.compact(&index)?;
+        Ok(index)
+    }
+}
+
+impl HistoryLine {
+    fn new(path: &Path, point: &HistoryPoint) -> HistoryLine {
+        HistoryLine {
+            path: path.to_path_buf(),
+            scanned_at: point.scanned_at.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+            size: point.size,
+            allocated_size: point.allocated_size,
+            item_count: point.item_count,
+        }
+    }
+}
+
+impl HistoryIndex {
+    // Oldest first, empty for folders that were never scanned
+    pub fn points(&self, folder: &Path) -> &[HistoryPoint] {
+        self.by_path.get(folder).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn folder_count(&self) -> usize {
+        self.by_path.len()
+    }
+
+    // What a scan adds to the history: the root and its direct subfolders every time,
+    // deeper folders only when their totals differ from their last point (or they have none yet)
+    pub fn scan_points(&self, tree: &DirTree, scanned_at: SystemTime) -> Vec<(PathBuf, HistoryPoint)> {
+        // The file only keeps whole seconds, do the same so both stay comparable
+        let secs = scanned_at.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let scanned_at = UNIX_EPOCH + Duration::from_secs(secs);
+        let root_depth = tree.node(tree.root()).entry.depth;
+
+        let mut points = Vec::new();
+        for id in tree.descendants(tree.root()) {
+            let node = tree.node(id);
+            if !node.entry.is_folder {
+                continue;
+            }
+            let point = HistoryPoint {
+                scanned_at,
+                size: node.total_size,
+                allocated_size: node.allocated_size,
+                item_count: node.item_count,
+            };
+            let top_level = node.entry.depth <= root_depth + 1;
+            let changed = self.points(&node.entry.path).last().is_none_or(|last| {
+                (last.size, last.allocated_size, last.item_count) != (point.size, point.allocated_size, point.item_count)
+            });
+            if top_level || changed {
+                points.push((node.entry.path.clone(), point));
+            }
+        }
+        points
+    }
+
+    // Points that were written to the file, so they show up without rereading it
+    pub fn add_points(&mut self, points: impl IntoIterator<Item = (PathBuf, HistoryPoint)>) {
+        for (path, point) in points {
+            self.insert(path, point);
+        }
+    }
+
+    // Fold in points recorded into another index, e.g. while this one was still loading
+    pub fn merge(&mut self, other: HistoryIndex) {
+        for (path, points) in other.by_path {
+            for point in points {
+                self.insert(path.clone(), point);
+            }
+        }
+    }
+
+    fn insert(&mut self, path: PathBuf, point: HistoryPoint) {
+        let points = self.by_path.entry(path).or_default();
+        // Points normally arrive in order, only an out of order one needs a sort
+        let in_order = points.last().is_none_or(|last| last.scanned_at <= point.scanned_at);
+        points.push(point);
+        if !in_order {
+            points.sort_by_key(|point| point.scanned_at);
+        }
+        if points.len() > MAX_POINTS {
+            let extra = points.len() - MAX_POINTS;
+            points.drain(..extra);
+            self.dropped += extra;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    use super::super::options::{scan_tree, ScanOptions};
+    use super::super::progress::ScanControl;
+    use super::super::testdir::TestDir;
+    use super::{History, HistoryIndex, HistoryPoint, MAX_POINTS};
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    fn point(secs: u64, size: u64) -> HistoryPoint {
+        HistoryPoint { scanned_at: at(secs), size, allocated_size: size, item_count: 1 }
+    }
+
+    // Recorded folders below the scan root
+    fn recorded(dir: &TestDir, points: &[(PathBuf, HistoryPoint)]) -> Vec<String> {
+        let mut paths: Vec<String> = points.iter().map(|(path, _)| path.strip_prefix(dir.path()).unwrap().to_string_lossy().to_string()).collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn scans_record_the_top_level_and_what_changed() {
+        let dir = TestDir::new("history");
+        dir.file("photos/2023/jan/a.jpg", 1000, 1);
+        dir.file("photos/2024/b.jpg", 2000, 2);
+        dir.file("music/c.mp3", 3000, 3);
+        let scan = || scan_tree(dir.path(), &ScanOptions::new(), &ScanControl::new()).unwrap();
+        let mut index = HistoryIndex::default();
+
+        // Nothing known yet, every folder is new
+        let first = index.scan_points(&scan(), at(1_000_000));
+        assert_eq!(recorded(&dir, &first), ["", "music", "photos", "photos/2023", "photos/2023/jan", "photos/2024"]);
+        index.add_points(first);
+
+        // Unchanged, only the root and its subfolders
+        let second = index.scan_points(&scan(), at(1_000_100));
+        assert_eq!(recorded(&dir, &second), ["", "music", "photos"]);
+        index.add_points(second);
+
+        // A deeper file grew: its folders are recorded, the untouched sibling isn't
+        dir.file("photos/2023/jan/a.jpg", 1500, 1);
+        let third = index.scan_points(&scan(), at(1_000_200));
+        assert_eq!(recorded(&dir, &third), ["", "music", "photos", "photos/2023", "photos/2023/jan"]);
+        index.add_points(third);
+
+        let jan = index.points(&dir.path().join("photos/2023/jan"));
+        // Folder totals include the folder entry itself, so compare the growth
+        assert_eq!(jan.len(), 2);
+        assert_eq!(jan[1].size - jan[0].size, 500);
+        assert_eq!(index.points(&dir.path().join("photos")).len(), 3);
+        assert!(index.points(Path::new("/never/scanned")).is_empty());
+    }
+
+    #[test]
+    fn folders_keep_at_most_max_points() {
+        let mut index = HistoryIndex::default();
+        let folder = Path::new("/data");
+        for secs in 0..MAX_POINTS as u64 + 5 {
+            index.insert(folder.to_path_buf(), point(secs, secs));
+        }
+        let points = index.points(folder);
+        assert_eq!(points.len(), MAX_POINTS);
+        // The oldest ones went
+        assert_eq!(points[0].scanned_at, at(5));
+        assert_eq!(index.dropped, 5);
+
+        // Out of order points are sorted in, then the oldest is still the one to go
+        index.insert(folder.to_path_buf(), point(50, 0));
+        let points = index.points(folder);
+        assert_eq!(points.len(), MAX_POINTS);
+        assert_eq!(points[0].scanned_at, at(6));
+        assert!(points.windows(2).all(|pair| pair[0].scanned_at <= pair[1].scanned_at));
+    }
+
+    #[test]
+    fn compacting_rewrites_the_file_through_a_temp_file() {
+        let dir = TestDir::new("history-file");
+        let history = History::open(&dir.path().join("data/history.ndjson"));
+        // A missing file is an empty history
+        assert_eq!(history.load().unwrap().folder_count(), 0);
+
+        history.append(&[(PathBuf::from("/a"), point(10, 1)), (PathBuf::from("/b"), point(10, 2))]).unwrap();
+        history.append(&[(PathBuf::from("/a"), point(20, 3))]).unwrap();
+        let mut contents = fs::read_to_string(history.file()).unwrap();
+        contents.push_str("{\"half a line\n");
+        fs::write(history.file(), &contents).unwrap();
+
+        let index = history.load().unwrap();
+        assert_eq!(index.dropped, 1);
+        assert_eq!(index.points(Path::new("/a")).len(), 2);
+
+        let index = history.load_compacted().unwrap();
+        assert_eq!(index.folder_count(), 2);
+        let compacted = fs::read_to_string(history.file()).unwrap();
+        assert_eq!(compacted.lines().count(), 3);
+        assert!(!compacted.contains("half a line"));
+        assert!(!history.file().with_extension("ndjson.tmp").exists());
+
+        // Nothing dropped any more, so nothing to rewrite
+        let reloaded = history.load().unwrap();
+        assert_eq!(reloaded.dropped, 0);
+        assert_eq!(reloaded.points(Path::new("/a")), index.points(Path::new("/a")));
+        history.compact(&reloaded).unwrap();
+        assert_eq!(fs::read_to_string(history.file()).unwrap(), compacted);
+    }
+}
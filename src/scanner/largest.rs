@@ -0,0 +1,132 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use super::tree::{DirTree, NodeId};
+
+// The biggest files and folders anywhere below a folder, largest first
+#[derive(Debug, Clone, Default)]
+pub struct LargestEntries {
+    pub files: Vec<NodeId>,
+    pub folders: Vec<NodeId>,
+}
+
+// The `limit` largest files and `limit` largest folders under `root`, `root` itself not included.
+// Only `limit` of each are held at any time, so this stays cheap on trees with millions of entries.
+// Equal sizes keep walk order.
+pub fn largest_entries(tree: &DirTree, root: NodeId, limit: usize, allocated: bool) -> LargestEntries {
+    let mut files = TopN::new(limit);
+    let mut folders = TopN::new(limit);
+    for id in tree.descendants(root).into_iter().skip(1) {
+        let node = tree.node(id);
+        let size = if allocated { node.allocated_size } else { node.total_size };
+        if node.entry.is_folder {
+            folders.push(size, id);
+        } else {
+            files.push(size, id);
+        }
+    }
+    LargestEntries { files: files.into_sorted(), folders: folders.into_sorted() }
+}
+
+// Min-heap of the largest sizes seen so far, its top is the first to go when a bigger one comes along.
+// Among equal sizes the later node is the smaller one.
+struct TopN {
+    limit: usize,
+    heap: BinaryHeap<Reverse<(u64, Reverse<NodeId>)>>,
+}
+
+impl TopN {
+    fn new(limit: usize) -> TopN {
+        TopN { limit, heap: BinaryHeap::with_capacity(limit.saturating_add(1).min(4096)) }
+    }
+
+    fn push(&mut self, size: u64, id: NodeId) {
+        if self.limit == 0 {
+            return;
+        }
+        let item = Reverse((size, Reverse(id)));
+        if self.heap.len() < self.limit {
+            self.heap.push(item);
+        } else if self.heap.peek().is_some_and(|smallest| item < *smallest) {
+            self.heap.pop();
+            self.heap.push(item);
+        }
+    }
+
+    fn into_sorted(self) -> Vec<NodeId> {
+        // Ascending in Reverse is descending in size
+        self.heap.into_sorted_vec().into_iter().map(|Reverse((_, Reverse(id)))| id).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::ncdu::read_ncdu;
+    use std::path::Path;
+    use super::super::tree::{DirTree, NodeId};
+    use super::{largest_entries, TopN};
+
+    // Files a..f, with c and d the same size, in folders that hold 700, 210 and 90 bytes
+    fn tree() -> DirTree {
+        let export = r#"[1, 2, {}, [{"name": "/data"},
+            [{"name": "big"}, {"name": "a", "asize": 500, "dsize": 512}, {"name": "b", "asize": 200, "dsize": 4096}],
+            [{"name": "mid"}, {"name": "c", "asize": 100}, {"name": "d", "asize": 100}, [{"name": "inner"}, {"name": "e", "asize": 10}]],
+            [{"name": "small"}, {"name": "f", "asize": 90}]
+        ]]"#;
+        read_ncdu(export.as_bytes()).unwrap()
+    }
+
+    fn names(tree: &DirTree, ids: &[NodeId]) -> Vec<String> {
+        ids.iter().map(|&id| tree.node(id).entry.name.clone()).collect()
+    }
+
+    #[test]
+    fn keeps_the_largest_first() {
+        let tree = tree();
+        let largest = largest_entries(&tree, tree.root(), 3, false);
+        assert_eq!(names(&tree, &largest.files), ["a", "b", "c"]);
+        assert_eq!(names(&tree, &largest.folders), ["big", "mid", "small"]);
+
+        // By disk usage b comes first
+        let largest = largest_entries(&tree, tree.root(), 1, true);
+        assert_eq!(names(&tree, &largest.files), ["b"]);
+        assert_eq!(names(&tree, &largest.folders), ["big"]);
+
+        // Only what is below the folder asked about, not the folder itself
+        let mid = tree.find(Path::new("/data/mid")).unwrap();
+        let largest = largest_entries(&tree, mid, 10, false);
+        assert_eq!(names(&tree, &largest.files), ["c", "d", "e"]);
+        assert_eq!(names(&tree, &largest.folders), ["inner"]);
+    }
+
+    #[test]
+    fn limits_at_both_ends() {
+        let tree = tree();
+        let none = largest_entries(&tree, tree.root(), 0, false);
+        assert!(none.files.is_empty() && none.folders.is_empty());
+
+        // More room than entries: all of them
+        let all = largest_entries(&tree, tree.root(), 100, false);
+        assert_eq!(names(&tree, &all.files), ["a", "b", "c", "d", "f", "e"]);
+        assert_eq!(all.folders.len(), 4);
+    }
+
+    #[test]
+    fn equal_sizes_keep_walk_order() {
+        let mut top = TopN::new(3);
+        for (size, id) in [(5, 0), (7, 1), (5, 2), (5, 3), (9, 4), (5, 5)] {
+            top.push(size, id);
+            assert!(top.heap.len() <= 3);
+        }
+        assert_eq!(top.into_sorted(), [4, 1, 0]);
+
+        let mut top = TopN::new(2);
+        for id in 0..5 {
+            top.push(1, id);
+        }
+        assert_eq!(top.into_sorted(), [0, 1]);
+
+        let mut top = TopN::new(0);
+        top.push(1, 0);
+        assert!(top.into_sorted().is_empty());
+    }
+}
@@ -2,8 +2,42 @@ pub mod directory;
 pub mod stats;
 pub mod utils; 
 pub mod actions;
+pub mod mounts;
+pub mod tree;
+pub mod parallel;
+pub mod progress;
+pub mod options;
+pub mod exclude;
+pub mod report;
+pub mod export;
+pub mod ncdu;
+pub mod snapshot;
+pub mod diff;
+pub mod history;
+pub mod duplicates;
+pub mod empty;
+pub mod symlinks;
+pub mod largest;
+#[cfg(test)]
+mod testdir;
 
 
-pub use directory::{FileEntry, FolderStats, scan_directory, compute_folder_stats};
-pub use actions::{prompt_and_open_folder, prompt_and_delete_file};
-pub use stats::{drive_usage};
\ No newline at end of file
+pub use directory::{FileEntry, FileKind, FolderStats, InodeHotspot, scan_directory, scan_directory_with, scan_directory_report, compute_folder_stats, compute_folder_stats_with, inode_hotspots};
+pub use actions::{prompt_and_open_folder, prompt_and_delete_file, LinkMode, LinkReport, link_duplicates, replace_with_link, remove_empty_folder, retarget_symlink};
+pub use tree::{DirNode, DirTree, NodeId};
+pub use parallel::{scan_parallel, scan_parallel_with, scan_directory_parallel};
+pub use progress::{ScanControl, ScanProgress};
+pub use options::{ScanOptions, SortOrder, scan_tree};
+pub use exclude::{ExcludeRules, IgnoreStack};
+pub use report::{ScanError, ScanErrorKind, ScanReport};
+pub use export::{ExportFormat, write_entries, write_folder_stats, write_drive_usage, write_scan};
+pub use ncdu::{read_ncdu, write_ncdu};
+pub use snapshot::{Snapshot, SNAPSHOT_EXTENSION, save_snapshot, load_snapshot, read_snapshot, write_snapshot};
+pub use diff::{Change, PathDelta, SizePair, TreeDiff, diff_trees, diff_snapshots};
+pub use duplicates::{DuplicateGroup, Duplicates, same_size_groups, confirm_duplicates, find_duplicates};
+pub use empty::{EmptyEntries, find_empty};
+pub use largest::{LargestEntries, largest_entries};
+pub use symlinks::{LinkStatus, SymlinkEntry, find_symlinks, inspect_symlink};
+pub use history::{History, HistoryIndex, HistoryPoint};
+pub use mounts::{MountEntry, list_mounts};
+pub use stats::{DriveUsage, drive_usage, find_mount_point};
\ No newline at end of file
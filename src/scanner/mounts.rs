@@ -0,0 +1,190 @@
+use std::io;
+use std::path::PathBuf;
+use super::stats::DriveUsage;
+
+#[derive(Debug, Clone)]
+pub struct MountEntry {
+    pub mount_id: u32,
+    pub parent_id: u32,
+    pub device: String,      // major:minor of the backing device
+    pub source: String,      // e.g. /dev/sda1, tmpfs, server:/export
+    pub fs_type: String,
+    pub root: PathBuf,       // path inside the filesystem that is mounted (bind mounts)
+    pub mount_point: PathBuf,
+    pub mount_options: Vec<String>,
+    pub super_options: Vec<String>,
+    pub usage: Option<DriveUsage>,
+}
+
+impl MountEntry {
+    pub fn is_read_only(&self) -> bool {
+        self.mount_options.iter().any(|opt| opt == "ro")
+    }
+}
+
+// Every mounted filesystem visible to this process, with usage filled in where statvfs works
+#[cfg(target_os = "linux")]
+pub fn list_mounts() -> io::Result<Vec<MountEntry>> {
+    let contents = std::fs::read_to_string("/proc/self/mountinfo")?;
+    let mut mounts = parse_mountinfo(&contents);
+
+    for mount in &mut mounts {
+        mount.usage = super::stats::statvfs(&mount.mount_point).ok();
+    }
+
+    Ok(mounts)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn list_mounts() -> io::Result<Vec<MountEntry>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "mount table is only available on linux",
+    ))
+}
+
+// Format (see proc(5)):
+// 36 35 98:0 /mnt1 /mnt2 rw,noatime master:1 - ext3 /dev/root rw,errors=continue
+pub fn parse_mountinfo(contents: &str) -> Vec<MountEntry> {
+    contents.lines().filter_map(parse_mountinfo_line).collect()
+}
+
+fn parse_mountinfo_line(line: &str) -> Option<MountEntry> {
+    // The optional fields have a variable length, so split on the " - " separator first
+    let (left, right) = line.split_once(" - ")?;
+
+    let mut left = left.split_whitespace();
+    let mount_id = left.next()?.parse().ok()?;
+    let parent_id = left.next()?.parse().ok()?;
+    let device = left.next()?.to_string();
+    let root = PathBuf::from(unescape(left.next()?));
+    let mount_point = PathBuf::from(unescape(left.next()?));
+    let mount_options = split_options(left.next()?);
+
+    let mut right = right.split_whitespace();
+    let fs_type = right.next()?.to_string();
+    let source = unescape(right.next().unwrap_or("none"));
+    let super_options = split_options(right.next().unwrap_or(""));
+
+    Some(MountEntry {
+        mount_id,
+        parent_id,
+        device,
+        source,
+        fs_type,
+        root,
+        mount_point,
+        mount_options,
+        super_options,
+        usage: None,
+    })
+}
+
+fn split_options(options: &str) -> Vec<String> {
+    options
+        .split(',')
+        .filter(|opt| !opt.is_empty())
+        .map(|opt| opt.to_string())
+        .collect()
+}
+
+// The kernel escapes space, tab, newline and backslash as \040, \011, \012 and \134
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        // Exactly three octal digits, from_str_radix alone would also take a sign
+        if bytes[i] == b'\\' && i + 4 <= bytes.len() && bytes[i + 1..i + 4].iter().all(|b| (b'0'..=b'7').contains(b)) {
+            let octal = std::str::from_utf8(&bytes[i + 1..i + 4]).unwrap_or("");
+            if let Ok(value) = u8::from_str_radix(octal, 8) {
+                out.push(value);
+                i += 4;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&out).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use super::{parse_mountinfo, unescape};
+
+    // Lines as /proc/self/mountinfo has them, the USB stick's label has a space in it
+    const MOUNTINFO: &str = "\
+22 1 259:2 / / rw,relatime shared:1 - ext4 /dev/nvme0n1p2 rw,errors=remount-ro
+23 22 0:21 / /proc rw,nosuid,nodev,noexec,relatime shared:12 - proc proc rw
+41 22 0:36 / /tmp rw,nosuid,nodev - tmpfs tmpfs rw,size=8093852k,inode64
+87 22 8:17 / /media/me/USB\\040Stick rw,nosuid,nodev,relatime shared:45 master:3 propagate_from:2 - vfat /dev/sdb1 ro,uid=1000
+95 22 259:2 /home/me/src /srv/src ro,relatime shared:1 - ext4 /dev/nvme0n1p2 rw
+";
+
+    #[test]
+    fn fields_are_read_around_the_optional_ones() {
+        let mounts = parse_mountinfo(MOUNTINFO);
+        assert_eq!(mounts.len(), 5);
+
+        let root = &mounts[0];
+        assert_eq!((root.mount_id, root.parent_id), (22, 1));
+        assert_eq!(root.device, "259:2");
+        assert_eq!(root.fs_type, "ext4");
+        assert_eq!(root.source, "/dev/nvme0n1p2");
+        assert_eq!(root.mount_options, ["rw", "relatime"]);
+        assert_eq!(root.super_options, ["rw", "errors=remount-ro"]);
+        assert!(root.usage.is_none());
+
+        // No optional fields at all
+        assert_eq!(mounts[2].mount_point, Path::new("/tmp"));
+        assert_eq!(mounts[2].fs_type, "tmpfs");
+
+        // Three optional fields, and an escaped space in the mount point
+        let stick = &mounts[3];
+        assert_eq!(stick.mount_point, Path::new("/media/me/USB Stick"));
+        assert_eq!(stick.fs_type, "vfat");
+        assert_eq!(stick.source, "/dev/sdb1");
+        assert!(!stick.is_read_only());
+
+        // A bind mount of a folder, read-only where it is mounted
+        let bind = &mounts[4];
+        assert_eq!(bind.root, Path::new("/home/me/src"));
+        assert!(bind.is_read_only());
+    }
+
+    #[test]
+    fn broken_lines_are_skipped() {
+        let contents = "
+not a mountinfo line
+x 22 0:21 / /proc rw - proc proc rw
+23 22 0:21 / /proc rw shared:12 proc proc rw
+24 22 0:21 / - proc proc rw
+25 22 0:21 / /sys rw -
+26 22 0:22 / /sys rw - sysfs
+";
+        let mounts = parse_mountinfo(contents);
+        // Only the last one, without a source or super options
+        assert_eq!(mounts.len(), 1);
+        assert_eq!(mounts[0].mount_point, Path::new("/sys"));
+        assert_eq!(mounts[0].source, "none");
+        assert!(mounts[0].super_options.is_empty());
+    }
+
+    #[test]
+    fn octal_escapes_are_decoded() {
+        assert_eq!(unescape("/mnt/a\\040b"), "/mnt/a b");
+        assert_eq!(unescape("tab\\011new\\012line"), "tab\tnew\nline");
+        assert_eq!(unescape("back\\134slash"), "back\\slash");
+        assert_eq!(unescape("caf\\303\\251"), "café");
+        // Anything else is left as it is
+        assert_eq!(unescape("\\04"), "\\04");
+        assert_eq!(unescape("\\+12"), "\\+12");
+        assert_eq!(unescape("\\999"), "\\999");
+        assert_eq!(unescape("\\777"), "\\777");
+        assert_eq!(unescape("plain"), "plain");
+    }
+}
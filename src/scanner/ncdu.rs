@@ -0,0 +1,256 @@
+use std::collections::HashSet;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use serde::Serialize;
+use serde_json::{json, Value};
+use super::directory::{FileEntry, FileKind};
+use super::report::{ScanError, ScanErrorKind};
+use super::tree::{DirTree, NodeId};
+
+// ncdu's JSON dump (`ncdu -o file` / `ncdu -f file`), format version 1.2:
+//   [1, 2, {"progname": .., "progver": .., "timestamp": ..}, <root dir>]
+// where a directory is an array [{info}, child, child, ..] and anything else is an {info} object.
+
+const MAJOR_VERSION: u64 = 1;
+const MINOR_VERSION: u64 = 2;
+
+// One entry's info object, in the key order ncdu itself writes
+#[derive(Serialize)]
+struct NcduInfo {
+    name: String,
+    asize: u64,
+    dsize: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dev: Option<u64>, // only when it differs from the parent directory
+    ino: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hlnkc: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nlink: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    notreg: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    read_error: Option<bool>,
+    uid: u32,
+    gid: u32,
+    mode: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mtime: Option<u64>,
+}
+
+// Write the subtree under `id`; extended info (mode, uid, gid, mtime) is always included, like `ncdu -e`
+pub fn write_ncdu<W: Write>(mut writer: W, tree: &DirTree, id: NodeId) -> io::Result<()> {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let header = json!({
+        "progname": "DiskScanner",
+        "progver": env!("CARGO_PKG_VERSION"),
+        "timestamp": timestamp,
+    });
+    write!(writer, "[{},{},{}", MAJOR_VERSION, MINOR_VERSION, header)?;
+
+    // ncdu marks directories it couldn't list, not the failing children
+    let unreadable: HashSet<&Path> = tree.report().errors.iter().map(|error| error.path.as_path()).collect();
+    writer.write_all(b",\n")?;
+    write_node(&mut writer, tree, id, None, &unreadable)?;
+    writer.write_all(b"]\n")?;
+    writer.flush()
+}
+
+fn write_node<W: Write>(
+    writer: &mut W,
+    tree: &DirTree,
+    id: NodeId,
+    parent_dev: Option<u64>,
+    unreadable: &HashSet<&Path>,
+) -> io::Result<()> {
+    let node = tree.node(id);
+    let entry = &node.entry;
+
+    let unix_secs = |time: SystemTime| time.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs());
+    let is_hardlinked = !entry.is_folder && entry.nlink > 1;
+    let info = NcduInfo {
+        // The root carries its full path, everything else just its name
+        name: if parent_dev.is_none() { entry.path.to_string_lossy().to_string() } else { entry.name.clone() },
+        asize: entry.size,
+        dsize: entry.allocated_size,
+        dev: (parent_dev != Some(entry.dev)).then_some(entry.dev),
+        ino: entry.inode,
+        hlnkc: is_hardlinked.then_some(true),
+        nlink: is_hardlinked.then_some(entry.nlink),
+        notreg: (!entry.is_folder && entry.file_type != FileKind::File).then_some(true),
+        read_error: (entry.is_folder && unreadable.contains(entry.path.as_path())).then_some(true),
+        uid: entry.uid,
+        gid: entry.gid,
+        mode: entry.mode,
+        mtime: entry.modified.and_then(unix_secs),
+    };
+
+    if !entry.is_folder {
+        return serde_json::to_writer(&mut *writer, &info).map_err(io::Error::from);
+    }
+
+    writer.write_all(b"[")?;
+    serde_json::to_writer(&mut *writer, &info)?;
+    for &child in tree.children(id) {
+        writer.write_all(b",\n")?;
+        write_node(writer, tree, child, Some(entry.dev), unreadable)?;
+    }
+    writer.write_all(b"]")
+}
+
+// Build a DirTree from an ncdu dump without touching the filesystem it describes
+pub fn read_ncdu<R: Read>(reader: R) -> io::Result<DirTree> {
+    let value: Value = serde_json::from_reader(reader)?;
+    let parts = value.as_array().ok_or_else(|| invalid("not an ncdu export, expected a JSON array"))?;
+    if parts.first().and_then(Value::as_u64) != Some(MAJOR_VERSION) {
+        return Err(invalid("unsupported ncdu export version"));
+    }
+    let root = parts.get(3).ok_or_else(|| invalid("ncdu export has no directory tree"))?;
+
+    let mut import = Import { tree: DirTree::default(), next_inode: u64::MAX };
+    import.read_node(root, None, Path::new(""), 0, 0)?;
+    if import.tree.is_empty() {
+        return Err(invalid("ncdu export has an empty tree"));
+    }
+
+    let mut tree = import.tree;
+    tree.aggregate();
+    Ok(tree)
+}
+
+struct Import {
+    tree: DirTree,
+    next_inode: u64, // made up inode numbers for entries exported without one, counting down
+}
+
+impl Import {
+    fn read_node(&mut self, value: &Value, parent: Option<NodeId>, parent_path: &Path, parent_dev: u64, depth: u32) -> io::Result<()> {
+        let (info, children) = match value {
+            Value::Array(items) => {
+                let (info, children) = items.split_first().ok_or_else(|| invalid("empty directory array"))?;
+                (info, Some(children))
+            }
+            info => (info, None),
+        };
+        let info = info.as_object().ok_or_else(|| invalid("expected an info object"))?;
+
+        let name = info.get("name").and_then(Value::as_str).ok_or_else(|| invalid("entry without a name"))?;
+        let path = if parent.is_none() { PathBuf::from(name) } else { parent_path.join(name) };
+        let number = |key: &str| info.get(key).and_then(Value::as_u64);
+        let flag = |key: &str| info.get(key).and_then(Value::as_bool).unwrap_or(false);
+
+        // Excluded entries were never counted by ncdu, their size (if any) goes to the excluded total
+        if info.contains_key("excluded") {
+            if let Some(parent) = parent {
+                self.tree.add_excluded(parent, number("asize").unwrap_or(0));
+            }
+            return Ok(());
+        }
+
+        let is_folder = children.is_some();
+        let file_type = if is_folder {
+            FileKind::Directory
+        } else if flag("notreg") {
+            FileKind::Other
+        } else {
+            FileKind::File
+        };
+        let dev = number("dev").unwrap_or(parent_dev);
+        let inode = number("ino").unwrap_or_else(|| {
+            self.next_inode -= 1;
+            self.next_inode
+        });
+
+        let entry = FileEntry {
+            name: path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| name.to_string()),
+            path: path.clone(),
+            size: number("asize").unwrap_or(0),
+            allocated_size: number("dsize").unwrap_or(0),
+            modified: number("mtime").map(|secs| UNIX_EPOCH + Duration::from_secs(secs)),
+            accessed: None,
+            changed: None,
+            file_type,
+            mode: number("mode").unwrap_or(0) as u32,
+            uid: number("uid").unwrap_or(0) as u32,
+            gid: number("gid").unwrap_or(0) as u32,
+            dev,
+            inode,
+            nlink: number("nlink").unwrap_or(if flag("hlnkc") { 2 } else { 1 }),
+            depth,
+            is_folder,
+        };
+        let id = self.tree.push(parent, entry);
+
+        if flag("read_error") {
+            self.tree.record_error(Some(id), ScanError {
+                path: path.clone(),
+                kind: ScanErrorKind::Other,
+                message: "ncdu could not read this directory".to_string(),
+            });
+        }
+
+        for child in children.into_iter().flatten() {
+            self.read_node(child, Some(id), &path, dev, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use super::super::options::{scan_tree, ScanOptions};
+    use super::super::progress::ScanControl;
+    use super::super::testdir::TestDir;
+    use super::super::tree::DirTree;
+    use super::{read_ncdu, write_ncdu};
+
+    // Every node by its path below the root: folder or not, own sizes, recursive totals
+    fn summary(tree: &DirTree) -> Vec<(PathBuf, bool, u64, u64, u64, u64, usize)> {
+        let root = tree.path(tree.root());
+        let mut nodes: Vec<_> = tree
+            .descendants(tree.root())
+            .into_iter()
+            .map(|id| {
+                let node = tree.node(id);
+                let relative = node.entry.path.strip_prefix(root).unwrap().to_path_buf();
+                (relative, node.entry.is_folder, node.entry.size, node.entry.allocated_size, node.total_size, node.allocated_size, node.item_count)
+            })
+            .collect();
+        nodes.sort_by(|a, b| a.0.cmp(&b.0));
+        nodes
+    }
+
+    #[test]
+    fn export_then_import_keeps_the_tree() {
+        let dir = TestDir::new("ncdu");
+        dir.file("docs/report.pdf", 20_000, 1);
+        dir.file("docs/notes.txt", 300, 2);
+        dir.file("src/deep/main.rs", 4500, 3);
+        dir.dir("empty");
+        #[cfg(unix)]
+        std::fs::hard_link(dir.path().join("docs/report.pdf"), dir.path().join("docs/report-link.pdf")).unwrap();
+
+        let tree = scan_tree(dir.path(), &ScanOptions::new(), &ScanControl::new()).unwrap();
+        let mut exported = Vec::new();
+        write_ncdu(&mut exported, &tree, tree.root()).unwrap();
+        let imported = read_ncdu(exported.as_slice()).unwrap();
+
+        assert_eq!(summary(&imported), summary(&tree));
+        // The root carries its full path, so the import points at the folder that was scanned
+        assert_eq!(imported.path(imported.root()), dir.path());
+    }
+
+    #[test]
+    fn import_rejects_what_is_not_an_ncdu_export() {
+        assert!(read_ncdu(&b"{}"[..]).is_err());
+        assert!(read_ncdu(&b"[2,0,{}]"[..]).is_err());
+        assert!(read_ncdu(&b"[1,2,{}]"[..]).is_err());
+        assert!(read_ncdu(&b"[1,2,{},[{\"name\":\"root\"}]]"[..]).is_ok());
+    }
+}
@@ -0,0 +1,139 @@
+use std::fmt;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use super::parallel::scan_parallel_with;
+use super::progress::ScanControl;
+use super::tree::DirTree;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SortOrder {
+    #[default]
+    WalkOrder, // whatever order the filesystem returned
+    Name,
+    Size,      // largest first
+    Modified,  // newest first
+}
+
+impl SortOrder {
+    pub const ALL: [SortOrder; 4] = [
+        SortOrder::WalkOrder,
+        SortOrder::Name,
+        SortOrder::Size,
+        SortOrder::Modified,
+    ];
+}
+
+impl fmt::Display for SortOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SortOrder::WalkOrder => "Walk order",
+            SortOrder::Name => "Name",
+            SortOrder::Size => "Size",
+            SortOrder::Modified => "Last modified",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// How a scan walks the filesystem, e.g. ScanOptions::new().max_depth(Some(3)).one_file_system(true)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanOptions {
+    pub max_depth: Option<usize>,  // entries deeper than this are counted in their folder's totals but not listed
+    pub follow_symlinks: bool,
+    pub one_file_system: bool,     // don't cross into other mounts, like `du -x`
+    pub include_hidden: bool,      // dot files and dot directories
+    pub sort_order: SortOrder,
+    pub threads: usize,            // 0 uses every CPU, 1 runs the sequential walker
+    pub exclude: Vec<String>,      // .gitignore style patterns, matching subtrees are pruned
+    pub include: Vec<String>,      // when not empty, only files matching one of these are kept
+    pub use_ignore_files: bool,    // honor .gitignore and .ignore files found during the walk
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        ScanOptions {
+            max_depth: None,
+            follow_symlinks: false,
+            one_file_system: false,
+            include_hidden: true,
+            sort_order: SortOrder::WalkOrder,
+            threads: 0,
+            exclude: Vec::new(),
+            include: Vec::new(),
+            use_ignore_files: false,
+        }
+    }
+}
+
+impl ScanOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    pub fn one_file_system(mut self, one_file_system: bool) -> Self {
+        self.one_file_system = one_file_system;
+        self
+    }
+
+    pub fn include_hidden(mut self, include_hidden: bool) -> Self {
+        self.include_hidden = include_hidden;
+        self
+    }
+
+    pub fn sort_order(mut self, sort_order: SortOrder) -> Self {
+        self.sort_order = sort_order;
+        self
+    }
+
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    pub fn exclude<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.exclude = patterns.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn include<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.include = patterns.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn use_ignore_files(mut self, use_ignore_files: bool) -> Self {
+        self.use_ignore_files = use_ignore_files;
+        self
+    }
+
+    // The scan root itself is never hidden, even when it's a dot directory
+    pub(crate) fn skips_name(&self, name: &str, depth: usize) -> bool {
+        depth > 0 && !self.include_hidden && name.starts_with('.')
+    }
+}
+
+// Entry point for callers that just want a tree: picks the walker from `options.threads`
+pub fn scan_tree(path: &Path, options: &ScanOptions, control: &ScanControl) -> Option<DirTree> {
+    if options.threads == 1 {
+        DirTree::scan_with(path, options, control)
+    } else {
+        scan_parallel_with(path, options, control)
+    }
+}
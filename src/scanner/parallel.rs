@@ -0,0 +1,351 @@
+use std::fs;
+use std::path::Path;
+use rayon::prelude::*;
+use super::directory::{entry_metadata, FileEntry, FileKind};
+use super::exclude::{self, ExcludeRules, IgnoreStack};
+use super::options::ScanOptions;
+use super::progress::ScanControl;
+use super::report::ScanError;
+use super::tree::{DirTree, NodeId};
+
+// A directory's entries, read by whichever worker got to it first
+struct Subtree {
+    entry: FileEntry,
+    children: Vec<Subtree>,
+    excluded_size: u64,
+    errors: Vec<ScanError>, // entries of this directory that couldn't be read
+}
+
+// What became of one directory entry
+enum Child {
+    Kept(Subtree),
+    Excluded(u64), // bytes the exclusion rules pruned
+    Failed(ScanError),
+}
+
+// Everything the workers share for one scan
+struct Walk<'a> {
+    options: &'a ScanOptions,
+    control: &'a ScanControl,
+    rules: ExcludeRules,
+    root_dev: u64,
+}
+
+// Same output as DirTree::scan, but every directory is read on a work-stealing thread pool.
+// `threads` of 0 uses one thread per CPU.
+pub fn scan_parallel(path: &Path, threads: usize) -> Option<DirTree> {
+    scan_parallel_with(path, &ScanOptions::new().threads(threads), &ScanControl::new())
+}
+
+// Parallel walk honoring `options`, reporting progress through `control`; None as well when cancelled
+pub fn scan_parallel_with(path: &Path, options: &ScanOptions, control: &ScanControl) -> Option<DirTree> {
+    let tree = walk(path, options, control);
+    control.finish();
+    if control.is_cancelled() {
+        return None;
+    }
+    let mut tree = tree?;
+    tree.sort_children(options.sort_order);
+    Some(tree)
+}
+
+fn walk(path: &Path, options: &ScanOptions, control: &ScanControl) -> Option<DirTree> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(options.threads)
+        .build()
+        .ok()?;
+
+    // Like WalkDir, a symlinked root is followed
+    let metadata = fs::metadata(path).ok()?;
+    let root = FileEntry::from_metadata(path, FileKind::from_file_type(metadata.file_type()), 0, &metadata);
+
+    let walk = Walk {
+        options,
+        control,
+        rules: ExcludeRules::new(path, options),
+        root_dev: root.dev,
+    };
+
+    control.record_entry(root.size);
+    let root = pool.install(|| walk.read_subtree(root, &[], &IgnoreStack::default()));
+    if control.is_cancelled() {
+        return None;
+    }
+
+    let mut tree = DirTree::default();
+    flatten(&mut tree, None, root);
+    tree.aggregate();
+    if let Some(max_depth) = options.max_depth {
+        tree.truncate_depth(max_depth);
+    }
+    Some(tree)
+}
+
+// Flat list in walk order, like scan_directory
+pub fn scan_directory_parallel(path: &Path, threads: usize) -> Vec<FileEntry> {
+    match scan_parallel(path, threads) {
+        Some(tree) => tree.nodes()[1..].iter().map(|node| node.entry.clone()).collect(),
+        None => Vec::new(),
+    }
+}
+
+impl Walk<'_> {
+    // `ancestors` holds the (dev, inode) of every directory above, to break symlink loops;
+    // `ignores` are the ignore files in effect in the directory holding `entry`
+    fn read_subtree(&self, entry: FileEntry, ancestors: &[(u64, u64)], ignores: &IgnoreStack) -> Subtree {
+        if !self.descends_into(&entry) {
+            return Subtree { entry, children: Vec::new(), excluded_size: 0, errors: Vec::new() };
+        }
+        self.control.enter_directory(&entry.path);
+        let ignores = if self.rules.is_active() { self.rules.enter(&entry.path, ignores) } else { IgnoreStack::default() };
+
+        // Keep read_dir order so the result matches the sequential walk exactly
+        let mut errors = Vec::new();
+        let mut dir_entries: Vec<fs::DirEntry> = Vec::new();
+        match fs::read_dir(&entry.path) {
+            Ok(read_dir) => {
+                for dir_entry in read_dir {
+                    match dir_entry {
+                        Ok(dir_entry) => dir_entries.push(dir_entry),
+                        Err(error) => errors.push(ScanError::from_io(&entry.path, &error)),
+                    }
+                }
+            }
+            Err(error) => errors.push(ScanError::from_io(&entry.path, &error)),
+        }
+
+        let mut ancestors = ancestors.to_vec();
+        ancestors.push((entry.dev, entry.inode));
+
+        let depth = entry.depth + 1;
+        let results: Vec<Child> = dir_entries
+            .into_par_iter()
+            .filter_map(|dir_entry| {
+                if self.control.is_cancelled() {
+                    return None;
+                }
+                let name = dir_entry.file_name();
+                if self.options.skips_name(&name.to_string_lossy(), depth as usize) {
+                    return None;
+                }
+
+                let path = dir_entry.path();
+                let metadata = match entry_metadata(&path, self.options.follow_symlinks) {
+                    Ok(metadata) => metadata,
+                    Err(error) => return Some(Child::Failed(ScanError::from_io(&path, &error))),
+                };
+                let file_type = if self.options.follow_symlinks {
+                    FileKind::from_file_type(metadata.file_type())
+                } else {
+                    match dir_entry.file_type() {
+                        Ok(file_type) => FileKind::from_file_type(file_type),
+                        Err(error) => return Some(Child::Failed(ScanError::from_io(&path, &error))),
+                    }
+                };
+
+                if self.rules.is_active() {
+                    let is_dir = file_type == FileKind::Directory;
+                    if self.rules.is_excluded(&path, is_dir, &ignores) {
+                        let root_dev = self.options.one_file_system.then_some(self.root_dev);
+                        return Some(Child::Excluded(exclude::excluded_size(&path, is_dir, root_dev, self.control)));
+                    }
+                }
+
+                let child = FileEntry::from_metadata(&path, file_type, depth, &metadata);
+                // A followed symlink pointing back at an ancestor is dropped, like WalkDir does
+                if child.is_folder && cfg!(unix) && ancestors.contains(&(child.dev, child.inode)) {
+                    return Some(Child::Failed(ScanError::symlink_loop(&path)));
+                }
+
+                self.control.record_entry(metadata.len());
+                Some(Child::Kept(self.read_subtree(child, &ancestors, &ignores)))
+            })
+            .collect();
+
+        let mut children = Vec::with_capacity(results.len());
+        let mut excluded_size = 0;
+        for result in results {
+            match result {
+                Child::Kept(child) => children.push(child),
+                Child::Excluded(bytes) => excluded_size += bytes,
+                Child::Failed(error) => errors.push(error),
+            }
+        }
+        Subtree { entry, children, excluded_size, errors }
+    }
+
+    // Same rule WalkDir applies for same_file_system; max_depth is applied to the finished tree
+    fn descends_into(&self, entry: &FileEntry) -> bool {
+        if !entry.is_folder || self.control.is_cancelled() {
+            return false;
+        }
+        !(self.options.one_file_system && entry.dev != self.root_dev)
+    }
+}
+
+// Parents go in before their children, the order DirTree::aggregate relies on
+fn flatten(tree: &mut DirTree, parent: Option<NodeId>, subtree: Subtree) {
+    let id = tree.push(parent, subtree.entry);
+    tree.add_excluded(id, subtree.excluded_size);
+    for error in subtree.errors {
+        tree.record_error(Some(id), error);
+    }
+    for child in subtree.children {
+        flatten(tree, Some(id), child);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::fs;
+    use std::os::unix::fs::{symlink, PermissionsExt};
+    use std::path::{Path, PathBuf};
+    use super::super::directory::FileKind;
+    use super::super::options::{scan_tree, ScanOptions};
+    use super::super::progress::ScanControl;
+    use super::super::report::ScanErrorKind;
+    use super::super::testdir::TestDir;
+    use super::super::tree::DirTree;
+
+    // What a scan saw, in an order that doesn't depend on the walker
+    #[derive(Debug, PartialEq)]
+    struct Seen {
+        entries: Vec<(PathBuf, FileKind, u64, u64, u64, usize, usize)>, // path, kind, size, total, allocated, items, errors
+        errors: Vec<(PathBuf, ScanErrorKind)>,
+    }
+
+    fn seen(tree: &DirTree) -> Seen {
+        let root = tree.path(tree.root());
+        let relative = |path: &Path| path.strip_prefix(root).unwrap().to_path_buf();
+        let mut entries: Vec<_> = tree
+            .descendants(tree.root())
+            .into_iter()
+            .map(|id| {
+                let node = tree.node(id);
+                let entry = &node.entry;
+                (relative(&entry.path), entry.file_type, entry.size, node.total_size, node.allocated_size, node.item_count, node.error_count)
+            })
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut errors: Vec<_> = tree.report().errors.iter().map(|error| (relative(&error.path), error.kind)).collect();
+        errors.sort();
+        Seen { entries, errors }
+    }
+
+    fn scan(root: &Path, threads: usize, follow_symlinks: bool) -> DirTree {
+        let options = ScanOptions::new().threads(threads).follow_symlinks(follow_symlinks);
+        scan_tree(root, &options, &ScanControl::new()).unwrap()
+    }
+
+    fn fixture() -> TestDir {
+        let dir = TestDir::new("walkers");
+        dir.file("a/big.bin", 100_000, 1);
+        dir.file("a/small.txt", 10, 2);
+        fs::hard_link(dir.path().join("a/big.bin"), dir.path().join("a/big-again.bin")).unwrap();
+        dir.file("a/deep/er/file", 5000, 3);
+        dir.dir("empty");
+        dir.file("locked/secret", 300, 4);
+        symlink("a/big.bin", dir.path().join("links-file")).unwrap();
+        symlink("a/deep", dir.path().join("links-dir")).unwrap();
+        symlink("missing", dir.path().join("dangling")).unwrap();
+        symlink("loop-b", dir.path().join("loop-a")).unwrap();
+        symlink("loop-a", dir.path().join("loop-b")).unwrap();
+        symlink("..", dir.path().join("a/up")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn walkers_agree_with_and_without_following_links() {
+        let dir = fixture();
+        let locked = dir.path().join("locked");
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+        // Root reads it anyway, then there is nothing unreadable to compare
+        let unreadable = fs::read_dir(&locked).is_err();
+
+        for follow in [false, true] {
+            let sequential = seen(&scan(dir.path(), 1, follow));
+            let parallel = seen(&scan(dir.path(), 4, follow));
+            assert_eq!(sequential, parallel, "follow_symlinks = {}", follow);
+
+            // An unreadable folder is listed once, as a folder, with its error reported
+            let locked_entries: Vec<_> = sequential.entries.iter().filter(|entry| entry.0 == Path::new("locked")).collect();
+            assert_eq!(locked_entries.len(), 1);
+            assert_eq!(locked_entries[0].1, FileKind::Directory);
+            if unreadable {
+                assert!(sequential.errors.contains(&(PathBuf::from("locked"), ScanErrorKind::PermissionDenied)));
+            }
+        }
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn unfollowed_links_are_sized_as_links() {
+        let dir = fixture();
+        for threads in [1, 4] {
+            let tree = scan(dir.path(), threads, false);
+            let entry = |name: &str| tree.node(tree.find(&dir.path().join(name)).unwrap()).entry.clone();
+
+            let link = entry("links-file");
+            assert_eq!(link.file_type, FileKind::Symlink);
+            assert_eq!(link.size, "a/big.bin".len() as u64);
+            assert_eq!(entry("a/big.bin").size, 100_000);
+            // Broken links stay in the tree instead of turning into errors
+            assert_eq!(entry("dangling").file_type, FileKind::Symlink);
+            assert_eq!(entry("loop-a").file_type, FileKind::Symlink);
+            assert!(tree.report().errors.iter().all(|error| error.path.file_name().unwrap() == "locked"));
+        }
+    }
+
+    #[test]
+    fn followed_links_count_their_targets() {
+        let dir = fixture();
+        for threads in [1, 4] {
+            let tree = scan(dir.path(), threads, true);
+            let node = |name: &str| tree.node(tree.find(&dir.path().join(name)).unwrap());
+
+            assert_eq!(node("links-file").entry.file_type, FileKind::File);
+            assert!(node("links-dir").entry.is_folder);
+            // Same inodes as a/deep, their bytes are only counted under whichever path came first
+            assert_eq!(node("links-dir").item_count, node("a/deep").item_count);
+            let deep: u64 = ["a/deep", "a/deep/er", "a/deep/er/file"].iter().map(|name| fs::metadata(dir.path().join(name)).unwrap().len()).sum();
+            assert_eq!(node("links-dir").total_size + node("a/deep").total_size, deep);
+            // A link that can't be followed is still the link itself
+            assert_eq!(node("dangling").entry.file_type, FileKind::Symlink);
+            assert_eq!(node("loop-b").entry.file_type, FileKind::Symlink);
+            // ..and one leading back up is a loop, not an endless walk
+            assert!(tree.report().errors.iter().any(|error| error.kind == ScanErrorKind::SymlinkLoop));
+        }
+    }
+
+    #[test]
+    fn max_depth_limits_what_is_listed_not_what_is_counted() {
+        let dir = fixture();
+        for threads in [1, 4] {
+            let full = scan(dir.path(), threads, false);
+            let options = ScanOptions::new().threads(threads).max_depth(Some(1));
+            let shallow = scan_tree(dir.path(), &options, &ScanControl::new()).unwrap();
+
+            // Nothing below the first level is listed...
+            assert!(shallow.nodes().iter().all(|node| node.entry.depth <= 1));
+            assert!(shallow.find(&dir.path().join("a/deep")).is_none());
+            assert!(shallow.children(shallow.find(&dir.path().join("a")).unwrap()).is_empty());
+
+            // ...but the folders at the limit hold all of it, like `du --max-depth`
+            for name in ["", "a", "locked", "empty"] {
+                let path = dir.path().join(name);
+                let (a, b) = (full.node(full.find(&path).unwrap()), shallow.node(shallow.find(&path).unwrap()));
+                assert_eq!(
+                    (a.total_size, a.allocated_size, a.item_count, a.file_count, a.subdir_count, a.extra_links, a.error_count),
+                    (b.total_size, b.allocated_size, b.item_count, b.file_count, b.subdir_count, b.extra_links, b.error_count),
+                    "{} with {} threads", name, threads,
+                );
+            }
+            assert_eq!(seen(&shallow).errors, seen(&full).errors);
+
+            // Depth 0 keeps just the root, with everything counted
+            let root_only = scan_tree(dir.path(), &options.clone().max_depth(Some(0)), &ScanControl::new()).unwrap();
+            assert_eq!(root_only.len(), 1);
+            assert_eq!(root_only.node(root_only.root()).total_size, full.node(full.root()).total_size);
+        }
+    }
+}
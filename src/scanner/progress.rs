@@ -0,0 +1,83 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Default)]
+pub struct ScanProgress {
+    pub files_seen: u64,
+    pub bytes_counted: u64,
+    pub current_path: PathBuf,
+    pub elapsed: Duration,
+    pub finished: bool,
+}
+
+// Shared between a running scan and whoever watches it; clones all point at the same scan
+#[derive(Debug, Clone)]
+pub struct ScanControl {
+    cancelled: Arc<AtomicBool>,
+    finished: Arc<AtomicBool>,
+    files_seen: Arc<AtomicU64>,
+    bytes_counted: Arc<AtomicU64>,
+    current_path: Arc<Mutex<PathBuf>>,
+    started: Instant,
+}
+
+impl Default for ScanControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScanControl {
+    pub fn new() -> Self {
+        ScanControl {
+            cancelled: Arc::new(AtomicBool::new(false)),
+            finished: Arc::new(AtomicBool::new(false)),
+            files_seen: Arc::new(AtomicU64::new(0)),
+            bytes_counted: Arc::new(AtomicU64::new(0)),
+            current_path: Arc::new(Mutex::new(PathBuf::new())),
+            started: Instant::now(),
+        }
+    }
+
+    // Ask the walk to stop; it returns at the next entry it looks at
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Relaxed)
+    }
+
+    pub fn progress(&self) -> ScanProgress {
+        ScanProgress {
+            files_seen: self.files_seen.load(Ordering::Relaxed),
+            bytes_counted: self.bytes_counted.load(Ordering::Relaxed),
+            current_path: self.current_path.lock().map(|path| path.clone()).unwrap_or_default(),
+            elapsed: self.started.elapsed(),
+            finished: self.is_finished(),
+        }
+    }
+
+    pub(crate) fn record_entry(&self, size: u64) {
+        self.files_seen.fetch_add(1, Ordering::Relaxed);
+        self.bytes_counted.fetch_add(size, Ordering::Relaxed);
+    }
+
+    // Only directories are reported, locking for every file would slow the walk down
+    pub(crate) fn enter_directory(&self, path: &Path) {
+        if let Ok(mut current) = self.current_path.lock() {
+            current.clear();
+            current.push(path);
+        }
+    }
+
+    pub(crate) fn finish(&self) {
+        self.finished.store(true, Ordering::Relaxed);
+    }
+}
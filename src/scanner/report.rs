@@ -0,0 +1,100 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum ScanErrorKind {
+    PermissionDenied,
+    NotFound,    // removed while the scan was running
+    SymlinkLoop, // a followed symlink pointing back at one of its ancestors
+    Other,
+}
+
+impl From<io::ErrorKind> for ScanErrorKind {
+    fn from(kind: io::ErrorKind) -> Self {
+        match kind {
+            io::ErrorKind::PermissionDenied => ScanErrorKind::PermissionDenied,
+            io::ErrorKind::NotFound => ScanErrorKind::NotFound,
+            _ => ScanErrorKind::Other,
+        }
+    }
+}
+
+impl fmt::Display for ScanErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ScanErrorKind::PermissionDenied => "Permission denied",
+            ScanErrorKind::NotFound => "Not found",
+            ScanErrorKind::SymlinkLoop => "Symlink loop",
+            ScanErrorKind::Other => "Other error",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// A path the walk could not read; whatever is below it is missing from the totals
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanError {
+    pub path: PathBuf,
+    pub kind: ScanErrorKind,
+    pub message: String,
+}
+
+impl ScanError {
+    pub fn from_io(path: &Path, error: &io::Error) -> Self {
+        ScanError {
+            path: path.to_path_buf(),
+            kind: error.kind().into(),
+            message: error.to_string(),
+        }
+    }
+
+    pub fn from_walkdir(error: &walkdir::Error) -> Self {
+        let path = error.path().map(Path::to_path_buf).unwrap_or_default();
+        let kind = if error.loop_ancestor().is_some() {
+            ScanErrorKind::SymlinkLoop
+        } else {
+            error.io_error().map(|e| e.kind().into()).unwrap_or(ScanErrorKind::Other)
+        };
+        ScanError { path, kind, message: error.to_string() }
+    }
+
+    pub fn symlink_loop(path: &Path) -> Self {
+        ScanError {
+            path: path.to_path_buf(),
+            kind: ScanErrorKind::SymlinkLoop,
+            message: format!("symlink loop at {}", path.display()),
+        }
+    }
+}
+
+// Every error a scan ran into, in the order they were met
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScanReport {
+    pub errors: Vec<ScanError>,
+}
+
+impl ScanReport {
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    pub fn push(&mut self, error: ScanError) {
+        self.errors.push(error);
+    }
+
+    // How many errors of each kind, e.g. for "Permission denied: 12"
+    pub fn counts(&self) -> BTreeMap<ScanErrorKind, usize> {
+        let mut counts = BTreeMap::new();
+        for error in &self.errors {
+            *counts.entry(error.kind).or_insert(0) += 1;
+        }
+        counts
+    }
+}
@@ -0,0 +1,235 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+use super::directory::{FileEntry, FileKind};
+use super::options::ScanOptions;
+use super::report::ScanReport;
+use super::tree::{DirNode, DirTree, NodeId};
+
+// "DSSNAP" + format version, then the bincode (varint) encoded SnapshotFile
+const MAGIC: &[u8; 6] = b"DSSNAP";
+const VERSION: u16 = 1;
+
+pub const SNAPSHOT_EXTENSION: &str = "dssnap";
+
+// A finished scan kept on disk so it can be browsed again without rescanning
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub root_path: PathBuf,
+    pub scanned_at: SystemTime,
+    pub options: ScanOptions,
+    pub tree: DirTree,
+}
+
+impl Snapshot {
+    pub fn new(tree: DirTree, options: ScanOptions, scanned_at: SystemTime) -> Snapshot {
+        Snapshot {
+            root_path: tree.path(tree.root()).to_path_buf(),
+            scanned_at,
+            options,
+            tree,
+        }
+    }
+
+    // How long ago the scan ran; zero if the clock went backwards since
+    pub fn age(&self) -> Duration {
+        SystemTime::now().duration_since(self.scanned_at).unwrap_or_default()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotFile {
+    root_path: PathBuf,
+    scanned_at: SystemTime,
+    options: ScanOptions,
+    nodes: Vec<NodeRecord>,
+    report: ScanReport,
+}
+
+// A DirNode without its full path or children list, both are rebuilt from `parent` on load
+#[derive(Serialize, Deserialize)]
+struct NodeRecord {
+    parent: Option<NodeId>,
+    name: String,
+    size: u64,
+    allocated_size: u64,
+    modified: Option<SystemTime>,
+    accessed: Option<SystemTime>,
+    changed: Option<SystemTime>,
+    file_type: FileKind,
+    mode: u32,
+    uid: u32,
+    gid: u32,
+    dev: u64,
+    inode: u64,
+    nlink: u64,
+    depth: u32,
+    is_folder: bool,
+    total_size: u64,
+    total_allocated_size: u64,
+    item_count: usize,
+    file_count: usize,
+    subdir_count: usize,
+    inode_count: usize,
+    shared_size: u64,
+    extra_links: usize,
+    excluded_size: u64,
+    error_count: usize,
+    last_modified: Option<SystemTime>,
+}
+
+pub fn save_snapshot(path: &Path, snapshot: &Snapshot) -> io::Result<()> {
+    write_snapshot(BufWriter::new(File::create(path)?), snapshot)
+}
+
+pub fn load_snapshot(path: &Path) -> io::Result<Snapshot> {
+    read_snapshot(BufReader::new(File::open(path)?))
+}
+
+pub fn write_snapshot<W: Write>(mut writer: W, snapshot: &Snapshot) -> io::Result<()> {
+    let tree = &snapshot.tree;
+
+    // Only what is reachable from the root, subtrees removed after deletes are left behind.
+    // Pre-order keeps every parent ahead of its children.
+    let order = tree.descendants(tree.root());
+    let mut new_ids = vec![usize::MAX; tree.len()];
+    for (new_id, &id) in order.iter().enumerate() {
+        new_ids[id] = new_id;
+    }
+
+    let nodes = order
+        .iter()
+        .map(|&id| {
+            let node = tree.node(id);
+            let entry = &node.entry;
+            NodeRecord {
+                parent: node.parent.map(|parent| new_ids[parent]),
+                name: entry.name.clone(),
+                size: entry.size,
+                allocated_size: entry.allocated_size,
+                modified: entry.modified,
+                accessed: entry.accessed,
+                changed: entry.changed,
+                file_type: entry.file_type,
+                mode: entry.mode,
+                uid: entry.uid,
+                gid: entry.gid,
+                dev: entry.dev,
+                inode: entry.inode,
+                nlink: entry.nlink,
+                depth: entry.depth,
+                is_folder: entry.is_folder,
+                total_size: node.total_size,
+                total_allocated_size: node.allocated_size,
+                item_count: node.item_count,
+                file_count: node.file_count,
+                subdir_count: node.subdir_count,
+                inode_count: node.inode_count,
+                shared_size: node.shared_size,
+                extra_links: node.extra_links,
+                excluded_size: node.excluded_size,
+                error_count: node.error_count,
+                last_modified: node.last_modified,
+            }
+        })
+        .collect();
+
+    let file = SnapshotFile {
+        root_path: snapshot.root_path.clone(),
+        scanned_at: snapshot.scanned_at,
+        options: snapshot.options.clone(),
+        nodes,
+        report: tree.report().clone(),
+    };
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    encoding().serialize_into(&mut writer, &file).map_err(|error| into_io(*error))?;
+    writer.flush()
+}
+
+pub fn read_snapshot<R: Read>(mut reader: R) -> io::Result<Snapshot> {
+    let mut header = [0u8; 8];
+    reader.read_exact(&mut header)?;
+    if &header[..6] != MAGIC {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not a DiskScanner snapshot"));
+    }
+    let version = u16::from_le_bytes([header[6], header[7]]);
+    if version != VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unsupported snapshot version {}", version),
+        ));
+    }
+
+    let file: SnapshotFile = encoding().deserialize_from(reader).map_err(|error| into_io(*error))?;
+    if file.nodes.is_empty() || file.nodes[0].parent.is_some() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "snapshot has no root folder"));
+    }
+
+    let mut nodes: Vec<DirNode> = Vec::with_capacity(file.nodes.len());
+    for (id, record) in file.nodes.into_iter().enumerate() {
+        let path = match record.parent {
+            Some(parent) if parent < id => nodes[parent].entry.path.join(&record.name),
+            Some(_) => return Err(io::Error::new(io::ErrorKind::InvalidData, "snapshot node listed before its parent")),
+            None => file.root_path.clone(),
+        };
+        nodes.push(DirNode {
+            entry: FileEntry {
+                path,
+                name: record.name,
+                size: record.size,
+                allocated_size: record.allocated_size,
+                modified: record.modified,
+                accessed: record.accessed,
+                changed: record.changed,
+                file_type: record.file_type,
+                mode: record.mode,
+                uid: record.uid,
+                gid: record.gid,
+                dev: record.dev,
+                inode: record.inode,
+                nlink: record.nlink,
+                depth: record.depth,
+                is_folder: record.is_folder,
+            },
+            parent: record.parent,
+            children: Vec::new(),
+            total_size: record.total_size,
+            allocated_size: record.total_allocated_size,
+            item_count: record.item_count,
+            file_count: record.file_count,
+            subdir_count: record.subdir_count,
+            inode_count: record.inode_count,
+            shared_size: record.shared_size,
+            extra_links: record.extra_links,
+            excluded_size: record.excluded_size,
+            error_count: record.error_count,
+            last_modified: record.last_modified,
+        });
+    }
+
+    let mut tree = DirTree::from_nodes(nodes, file.report);
+    tree.sort_children(file.options.sort_order);
+    Ok(Snapshot {
+        root_path: file.root_path,
+        scanned_at: file.scanned_at,
+        options: file.options,
+        tree,
+    })
+}
+
+// Variable length integers, most sizes and counts fit in a byte or two
+fn encoding() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+fn into_io(error: bincode::ErrorKind) -> io::Error {
+    match error {
+        bincode::ErrorKind::Io(error) => error,
+        other => io::Error::new(io::ErrorKind::InvalidData, other.to_string()),
+    }
+}
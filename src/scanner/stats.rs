@@ -1,45 +1,114 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::io;
-use super::utils::human_readable_size;
-
-pub fn drive_usage(_path: &PathBuf) -> (Vec<String>, f64) {
-    let mut output = Vec::new();
-    let mut used_percentage = 0.0;
-
-    if let Ok(total_space) = get_total_disk_space() {
-        if let Ok(free_space) = get_free_disk_space() {
-            let used_space = total_space - free_space;
-            used_percentage = (used_space as f64 / total_space as f64) * 100.0;
-            let free_percentage = 100.0 - used_percentage;
-
-            // vec
-            output.push("Drive Usage:".to_string());
-            output.push(format!("Total Space: {}", human_readable_size(total_space)));
-            output.push(format!(
-                "Used Space: {} ({:.2}%)",
-                human_readable_size(used_space),
-                used_percentage
-            ));
-            output.push(format!(
-                "Free Space: {} ({:.2}%)",
-                human_readable_size(free_space),
-                free_percentage
-            ));
-        } else {
-            output.push("Could not retrieve free space information.".to_string());
+
+#[derive(Debug, Clone)]
+pub struct DriveUsage {
+    pub mount_point: PathBuf,
+    pub total_space: u64,
+    pub free_space: u64,      // free blocks, including the ones reserved for root
+    pub available_space: u64, // free blocks an unprivileged user can actually write to
+    pub reserved_space: u64,
+    pub total_inodes: u64,
+    pub free_inodes: u64,
+}
+
+impl DriveUsage {
+    pub fn used_space(&self) -> u64 {
+        self.total_space.saturating_sub(self.free_space)
+    }
+
+    pub fn used_percentage(&self) -> f64 {
+        if self.total_space == 0 {
+            return 0.0;
+        }
+        (self.used_space() as f64 / self.total_space as f64) * 100.0
+    }
+
+    pub fn free_percentage(&self) -> f64 {
+        if self.total_space == 0 {
+            return 0.0;
         }
-    } else {
-        output.push("Could not retrieve total space information.".to_string());
+        100.0 - self.used_percentage()
+    }
+
+    pub fn used_inodes(&self) -> u64 {
+        self.total_inodes.saturating_sub(self.free_inodes)
     }
 
-    (output, used_percentage)
+    // Some filesystems (btrfs, FAT) report zero inodes since they allocate them dynamically
+    pub fn inode_percentage(&self) -> f64 {
+        if self.total_inodes == 0 {
+            return 0.0;
+        }
+        (self.used_inodes() as f64 / self.total_inodes as f64) * 100.0
+    }
 }
 
-fn get_total_disk_space() -> Result<u64, io::Error> {
-    Ok(1 << 40) 
+// Usage of the filesystem that contains `path`
+pub fn drive_usage(path: &Path) -> io::Result<DriveUsage> {
+    let mount_point = find_mount_point(path)?;
+    statvfs(&mount_point)
 }
 
-fn get_free_disk_space() -> Result<u64, io::Error> {
-    Ok(500 * (1 << 30))
+// Walk up from `path` until the parent lives on a different device
+#[cfg(unix)]
+pub fn find_mount_point(path: &Path) -> io::Result<PathBuf> {
+    use std::os::unix::fs::MetadataExt;
+
+    let path = path.canonicalize()?;
+    let dev = std::fs::metadata(&path)?.dev();
+
+    let mut mount_point = path.as_path();
+    while let Some(parent) = mount_point.parent() {
+        match std::fs::metadata(parent) {
+            Ok(meta) if meta.dev() == dev => mount_point = parent,
+            _ => break,
+        }
+    }
+
+    Ok(mount_point.to_path_buf())
 }
 
+#[cfg(not(unix))]
+pub fn find_mount_point(path: &Path) -> io::Result<PathBuf> {
+    let path = path.canonicalize()?;
+    Ok(path.ancestors().last().unwrap_or(&path).to_path_buf())
+}
+
+#[cfg(unix)]
+pub(crate) fn statvfs(path: &Path) -> io::Result<DriveUsage> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+    let mut buf: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: c_path is a valid NUL terminated string and buf is a valid out pointer
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut buf) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let block_size = buf.f_frsize as u64;
+    let total_space = buf.f_blocks as u64 * block_size;
+    let free_space = buf.f_bfree as u64 * block_size;
+    let available_space = buf.f_bavail as u64 * block_size;
+
+    Ok(DriveUsage {
+        mount_point: path.to_path_buf(),
+        total_space,
+        free_space,
+        available_space,
+        reserved_space: free_space.saturating_sub(available_space),
+        total_inodes: buf.f_files as u64,
+        free_inodes: buf.f_ffree as u64,
+    })
+}
+
+#[cfg(not(unix))]
+pub(crate) fn statvfs(_path: &Path) -> io::Result<DriveUsage> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "drive usage is only supported on unix systems",
+    ))
+}
@@ -0,0 +1,159 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use super::directory::FileKind;
+use super::report::ScanErrorKind;
+use super::tree::{DirTree, NodeId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LinkStatus {
+    Valid,
+    Ancestor,   // works, but leads to a folder the link is inside of, so following it recursively goes round in circles
+    Dangling,   // the target doesn't exist
+    Loop,       // the target can't be resolved, it leads back to the link itself (ELOOP)
+    Unreadable, // the target couldn't be checked, usually a folder on the way isn't accessible
+}
+
+impl fmt::Display for LinkStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LinkStatus::Valid => "Valid",
+            LinkStatus::Ancestor => "Ancestor",
+            LinkStatus::Dangling => "Dangling",
+            LinkStatus::Loop => "Loop",
+            LinkStatus::Unreadable => "Unreadable",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymlinkEntry {
+    pub path: PathBuf,
+    pub target: PathBuf, // as stored in the link, relative targets are relative to the link's folder
+    pub status: LinkStatus,
+}
+
+impl SymlinkEntry {
+    // Only links known not to resolve: ancestor links work and unreadable ones may well work too
+    pub fn is_broken(&self) -> bool {
+        matches!(self.status, LinkStatus::Dangling | LinkStatus::Loop)
+    }
+}
+
+// Read a symlink and check where it leads
+pub fn inspect_symlink(path: &Path) -> io::Result<SymlinkEntry> {
+    let target = fs::read_link(path)?;
+    let status = match fs::metadata(path) {
+        Ok(metadata) if metadata.is_dir() && points_at_ancestor(path) => LinkStatus::Ancestor,
+        Ok(_) => LinkStatus::Valid,
+        Err(error) if is_loop(&error) => LinkStatus::Loop,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => LinkStatus::Dangling,
+        Err(_) => LinkStatus::Unreadable,
+    };
+    Ok(SymlinkEntry { path: path.to_path_buf(), target, status })
+}
+
+// Every symlink under `root`, broken ones first, then by path.
+// With follow_symlinks the working links were scanned as what they point at, so only the broken ones
+// and the ones that looped back to an ancestor (kept in the scan report) are found.
+pub fn find_symlinks(tree: &DirTree, root: NodeId) -> Vec<SymlinkEntry> {
+    let root_path = tree.path(root);
+    let mut paths: Vec<&Path> = tree
+        .descendants(root)
+        .into_iter()
+        .filter(|&id| tree.node(id).entry.file_type == FileKind::Symlink)
+        .map(|id| tree.path(id))
+        .collect();
+    paths.extend(
+        tree.report()
+            .errors
+            .iter()
+            .filter(|error| error.kind == ScanErrorKind::SymlinkLoop && error.path.starts_with(root_path))
+            .map(|error| error.path.as_path()),
+    );
+    paths.sort();
+    paths.dedup();
+
+    // Links removed since the scan are left out
+    let mut links: Vec<SymlinkEntry> = paths.into_iter().filter_map(|path| inspect_symlink(path).ok()).collect();
+    links.sort_by(|a, b| b.is_broken().cmp(&a.is_broken()).then_with(|| a.path.cmp(&b.path)));
+    links
+}
+
+// A link to a folder that holds the link, following it goes round in circles
+fn points_at_ancestor(path: &Path) -> bool {
+    let (Ok(target), Some(Ok(folder))) = (fs::canonicalize(path), path.parent().map(fs::canonicalize)) else {
+        return false;
+    };
+    folder.starts_with(target)
+}
+
+#[cfg(unix)]
+fn is_loop(error: &io::Error) -> bool {
+    error.raw_os_error() == Some(libc::ELOOP)
+}
+
+#[cfg(not(unix))]
+fn is_loop(_error: &io::Error) -> bool {
+    false
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::fs::symlink;
+    use std::path::Path;
+    use super::super::options::{scan_tree, ScanOptions};
+    use super::super::progress::ScanControl;
+    use super::super::testdir::TestDir;
+    use super::{find_symlinks, inspect_symlink, LinkStatus};
+
+    fn fixture() -> TestDir {
+        let dir = TestDir::new("symlinks");
+        dir.file("a/file", 10, 1);
+        dir.dir("b");
+        let link = |target: &str, name: &str| symlink(target, dir.path().join(name)).unwrap();
+        link("file", "a/to-file");
+        link("../b", "a/to-sibling");
+        link("..", "a/up");
+        link("missing", "a/dangling");
+        link("loop-b", "loop-a");
+        link("loop-a", "loop-b");
+        link("itself", "itself");
+        dir
+    }
+
+    #[test]
+    fn links_are_told_apart_by_where_they_lead() {
+        let dir = fixture();
+        let status = |name: &str| inspect_symlink(&dir.path().join(name)).unwrap().status;
+        assert_eq!(status("a/to-file"), LinkStatus::Valid);
+        assert_eq!(status("a/to-sibling"), LinkStatus::Valid);
+        assert_eq!(status("a/up"), LinkStatus::Ancestor);
+        assert_eq!(status("a/dangling"), LinkStatus::Dangling);
+        assert_eq!(status("loop-a"), LinkStatus::Loop);
+        assert_eq!(status("itself"), LinkStatus::Loop);
+
+        let up = inspect_symlink(&dir.path().join("a/up")).unwrap();
+        assert_eq!(up.target, Path::new(".."));
+        assert!(!up.is_broken());
+        assert!(inspect_symlink(&dir.path().join("a/file")).is_err());
+    }
+
+    #[test]
+    fn only_links_that_dont_resolve_are_broken() {
+        let dir = fixture();
+        for follow_symlinks in [false, true] {
+            let tree = scan_tree(dir.path(), &ScanOptions::new().follow_symlinks(follow_symlinks), &ScanControl::new()).unwrap();
+            let links = find_symlinks(&tree, tree.root());
+            let broken: Vec<&Path> = links.iter().filter(|link| link.is_broken()).map(|link| link.path.strip_prefix(dir.path()).unwrap()).collect();
+            // Broken first, by path
+            assert_eq!(broken, [Path::new("a/dangling"), Path::new("itself"), Path::new("loop-a"), Path::new("loop-b")]);
+            assert!(links[..broken.len()].iter().all(|link| link.is_broken()));
+            // Followed, the working links were scanned as their targets; the one to an ancestor is still listed
+            assert!(links.iter().any(|link| link.status == LinkStatus::Ancestor));
+            assert_eq!(links.iter().any(|link| link.status == LinkStatus::Valid), !follow_symlinks);
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+// A scratch folder for one test, removed again when it goes out of scope
+pub struct TestDir {
+    path: PathBuf,
+}
+
+impl TestDir {
+    pub fn new(name: &str) -> TestDir {
+        let unique = format!("diskscanner-{}-{}-{}", name, std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed));
+        let path = std::env::temp_dir().join(unique);
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        // Scans report canonical paths, temp_dir() may go through a symlink
+        TestDir { path: path.canonicalize().unwrap() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn dir(&self, relative: &str) -> PathBuf {
+        let path = self.path.join(relative);
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    pub fn write(&self, relative: &str, contents: &[u8]) -> PathBuf {
+        let path = self.path.join(relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    // `len` bytes that differ from one offset to the next, seeded so two seeds never match
+    pub fn file(&self, relative: &str, len: usize, seed: u8) -> PathBuf {
+        let contents: Vec<u8> = (0..len).map(|i| (i % 251) as u8 ^ seed).collect();
+        self.write(relative, &contents)
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,405 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+use walkdir::WalkDir;
+use super::directory::{entry_metadata, FileEntry, FileKind, FolderStats, InodeHotspot};
+use super::exclude::{self, ExcludeRules, IgnoreStack};
+use super::options::{ScanOptions, SortOrder};
+use super::progress::ScanControl;
+use super::report::{ScanError, ScanReport};
+
+pub type NodeId = usize;
+
+#[derive(Debug, Clone)]
+pub struct DirNode {
+    pub entry: FileEntry,
+    pub parent: Option<NodeId>,
+    pub children: Vec<NodeId>,
+    // Everything below is aggregated over the whole subtree, the node itself included
+    pub total_size: u64,
+    pub allocated_size: u64,
+    pub item_count: usize, // descendants, the node itself excluded
+    pub file_count: usize,
+    pub subdir_count: usize,
+    pub inode_count: usize,
+    pub shared_size: u64,
+    pub extra_links: usize,
+    pub excluded_size: u64, // apparent size of entries pruned by the exclusion rules
+    pub error_count: usize, // paths that couldn't be read, see DirTree::report
+    pub last_modified: Option<SystemTime>,
+}
+
+// Arena of every scanned path, parents always stored before their children
+#[derive(Debug, Clone, Default)]
+pub struct DirTree {
+    nodes: Vec<DirNode>,
+    inodes: HashSet<(u64, u64)>,
+    report: ScanReport,
+}
+
+impl DirTree {
+    // Walk `path` once and build the whole tree, None if the root itself can't be read
+    pub fn scan(path: &Path) -> Option<DirTree> {
+        DirTree::scan_with(path, &ScanOptions::default(), &ScanControl::new())
+    }
+
+    // Sequential walk honoring `options`, reporting progress through `control`; None as well when cancelled
+    pub fn scan_with(path: &Path, options: &ScanOptions, control: &ScanControl) -> Option<DirTree> {
+        let tree = DirTree::walk(path, options, control);
+        control.finish();
+        if control.is_cancelled() {
+            return None;
+        }
+        let mut tree = tree?;
+        tree.sort_children(options.sort_order);
+        Some(tree)
+    }
+
+    fn walk(path: &Path, options: &ScanOptions, control: &ScanControl) -> Option<DirTree> {
+        let mut tree = DirTree::default();
+        let mut stack: Vec<NodeId> = Vec::new(); // stack[depth] is the open directory at that depth
+        let rules = ExcludeRules::new(path, options);
+        let mut ignores: Vec<IgnoreStack> = Vec::new(); // ignore files in effect inside stack[depth]
+
+        let mut walker = WalkDir::new(path)
+            .follow_links(options.follow_symlinks)
+            .same_file_system(options.one_file_system)
+            .into_iter()
+            .filter_entry(|entry| !options.skips_name(&entry.file_name().to_string_lossy(), entry.depth()));
+        while let Some(entry) = walker.next() {
+            if control.is_cancelled() {
+                return None;
+            }
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(error) => {
+                    let depth = error.depth();
+                    // A followed link that leads nowhere is kept as the link itself, like it is when links aren't followed.
+                    // Anything else (a folder that can't be listed, a file that vanished) stays an error.
+                    if let (Some(path), None, true) = (error.path(), error.loop_ancestor(), depth > 0) {
+                        let link = fs::symlink_metadata(path).ok().filter(|metadata| metadata.file_type().is_symlink());
+                        if let (Some(&parent), Some(metadata)) = (stack.get(depth - 1), link) {
+                            let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+                            let skipped = options.skips_name(&name, depth)
+                                || (rules.is_active() && rules.is_excluded(path, false, &ignores[depth - 1]));
+                            if !skipped {
+                                tree.push(Some(parent), FileEntry::from_metadata(path, FileKind::Symlink, depth as u32, &metadata));
+                                control.record_entry(metadata.len());
+                            }
+                            continue;
+                        }
+                    }
+                    // A directory that can't be listed reports its own path at its own depth
+                    let owner = match (stack.get(depth), error.path()) {
+                        (Some(&id), Some(path)) if tree.path(id) == path => Some(id),
+                        _ => depth.checked_sub(1).and_then(|parent| stack.get(parent).copied()),
+                    };
+                    tree.record_error(owner, ScanError::from_walkdir(&error));
+                    continue;
+                }
+            };
+            let depth = entry.depth();
+            let file_type = FileKind::from_file_type(entry.file_type());
+
+            // Like WalkDir, a symlinked root is followed
+            let follow = options.follow_symlinks || depth == 0;
+            let metadata = match entry_metadata(entry.path(), follow) {
+                Ok(meta) => meta,
+                Err(error) => {
+                    if depth == 0 {
+                        return None;
+                    }
+                    if entry.file_type().is_dir() {
+                        walker.skip_current_dir();
+                    }
+                    let parent = stack.get(depth - 1).copied();
+                    tree.record_error(parent, ScanError::from_io(entry.path(), &error));
+                    continue;
+                }
+            };
+
+            let parent = if depth == 0 { None } else { stack.get(depth - 1).copied() };
+            if rules.is_active() && depth > 0 {
+                let is_dir = entry.file_type().is_dir();
+                if rules.is_excluded(entry.path(), is_dir, &ignores[depth - 1]) {
+                    if let Some(parent) = parent {
+                        let root_dev = options.one_file_system.then(|| tree.node(tree.root()).entry.dev);
+                        tree.add_excluded(parent, exclude::excluded_size(entry.path(), is_dir, root_dev, control));
+                    }
+                    if is_dir {
+                        walker.skip_current_dir();
+                    }
+                    continue;
+                }
+            }
+
+            let id = tree.push(parent, FileEntry::from_metadata(entry.path(), file_type, depth as u32, &metadata));
+            control.record_entry(metadata.len());
+
+            if entry.file_type().is_dir() {
+                control.enter_directory(entry.path());
+                stack.truncate(depth);
+                stack.push(id);
+                if rules.is_active() {
+                    let inherited = if depth == 0 { IgnoreStack::default() } else { ignores[depth - 1].clone() };
+                    ignores.truncate(depth);
+                    ignores.push(rules.enter(entry.path(), &inherited));
+                }
+            }
+        }
+
+        if tree.nodes.is_empty() {
+            return None;
+        }
+        tree.aggregate();
+        if let Some(max_depth) = options.max_depth {
+            tree.truncate_depth(max_depth);
+        }
+        Some(tree)
+    }
+
+    // Reassemble a tree from already aggregated nodes (parents first), e.g. read back from a snapshot.
+    // Children lists and the hardlink set are rebuilt from the `parent` links.
+    pub(crate) fn from_nodes(mut nodes: Vec<DirNode>, report: ScanReport) -> DirTree {
+        let mut inodes = HashSet::new();
+        for id in 0..nodes.len() {
+            nodes[id].children.clear();
+            inodes.insert((nodes[id].entry.dev, nodes[id].entry.inode));
+            if let Some(parent) = nodes[id].parent {
+                nodes[parent].children.push(id);
+            }
+        }
+        DirTree { nodes, inodes, report }
+    }
+
+    // Add a node under `parent`; sizes are only rolled up by `aggregate`
+    pub fn push(&mut self, parent: Option<NodeId>, entry: FileEntry) -> NodeId {
+        let id = self.nodes.len();
+
+        // Hardlinks point at the same inode, so only the first path we meet pays for it (like du)
+        let first_link = !cfg!(unix) || self.inodes.insert((entry.dev, entry.inode));
+        let counted = |value: u64| if first_link { value } else { 0 };
+        let is_shared = first_link && !entry.is_folder && entry.nlink > 1;
+
+        self.nodes.push(DirNode {
+            parent,
+            children: Vec::new(),
+            total_size: counted(entry.size),
+            allocated_size: counted(entry.allocated_size),
+            item_count: 0,
+            file_count: usize::from(entry.file_type == FileKind::File),
+            subdir_count: usize::from(entry.is_folder),
+            inode_count: usize::from(first_link),
+            shared_size: if is_shared { entry.size } else { 0 },
+            extra_links: usize::from(!first_link),
+            excluded_size: 0,
+            error_count: 0,
+            last_modified: entry.modified,
+            entry,
+        });
+
+        if let Some(parent) = parent {
+            self.nodes[parent].children.push(id);
+        }
+        id
+    }
+
+    // Bytes an exclusion rule kept out of directory `id`; rolled up by `aggregate` like the rest
+    pub fn add_excluded(&mut self, id: NodeId, bytes: u64) {
+        self.nodes[id].excluded_size += bytes;
+    }
+
+    // Remember a path the walk couldn't read; `owner` is the directory whose totals are now short
+    pub fn record_error(&mut self, owner: Option<NodeId>, error: ScanError) {
+        if let Some(owner) = owner {
+            self.nodes[owner].error_count += 1;
+        }
+        self.report.push(error);
+    }
+
+    pub fn report(&self) -> &ScanReport {
+        &self.report
+    }
+
+    // Roll every node's totals up into its ancestors
+    pub fn aggregate(&mut self) {
+        for id in (1..self.nodes.len()).rev() {
+            let Some(parent) = self.nodes[id].parent else { continue };
+            // Parents always sit before their children, so both halves can be borrowed at once
+            let (before, after) = self.nodes.split_at_mut(id);
+            before[parent].add_totals(&after[0], 1);
+        }
+    }
+
+    // Drop every node more than `max_depth` levels below the root, after `aggregate`.
+    // Like `du --max-depth`, the folders at the limit keep the totals of everything that was under them.
+    // Node ids change.
+    pub fn truncate_depth(&mut self, max_depth: usize) {
+        let mut new_ids: Vec<Option<NodeId>> = vec![None; self.nodes.len()];
+        let mut kept = Vec::with_capacity(self.nodes.len());
+        for (id, mut node) in std::mem::take(&mut self.nodes).into_iter().enumerate() {
+            if node.entry.depth as usize > max_depth {
+                continue;
+            }
+            // Parents come first and are never deeper, so theirs is already known
+            node.parent = node.parent.and_then(|parent| new_ids[parent]);
+            new_ids[id] = Some(kept.len());
+            kept.push(node);
+        }
+        *self = DirTree::from_nodes(kept, std::mem::take(&mut self.report));
+    }
+
+    // Reorder every node's children; node ids stay the same
+    pub fn sort_children(&mut self, order: SortOrder) {
+        if order == SortOrder::WalkOrder {
+            return;
+        }
+
+        for id in 0..self.nodes.len() {
+            let mut children = std::mem::take(&mut self.nodes[id].children);
+            let nodes = &self.nodes;
+            match order {
+                SortOrder::WalkOrder => {}
+                SortOrder::Name => children.sort_by(|&a, &b| nodes[a].entry.name.cmp(&nodes[b].entry.name)),
+                SortOrder::Size => children.sort_by(|&a, &b| nodes[b].total_size.cmp(&nodes[a].total_size)),
+                SortOrder::Modified => children.sort_by(|&a, &b| nodes[b].last_modified.cmp(&nodes[a].last_modified)),
+            }
+            self.nodes[id].children = children;
+        }
+    }
+
+    pub fn root(&self) -> NodeId {
+        0
+    }
+
+    pub fn node(&self, id: NodeId) -> &DirNode {
+        &self.nodes[id]
+    }
+
+    pub fn nodes(&self) -> &[DirNode] {
+        &self.nodes
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        &self.nodes[id].children
+    }
+
+    pub fn path(&self, id: NodeId) -> &Path {
+        &self.nodes[id].entry.path
+    }
+
+    // Look a path up by walking down from the root one component at a time
+    pub fn find(&self, path: &Path) -> Option<NodeId> {
+        let relative = path.strip_prefix(self.path(self.root())).ok()?;
+        let mut id = self.root();
+
+        for component in relative.components() {
+            let name = component.as_os_str().to_string_lossy();
+            id = *self.nodes[id]
+                .children
+                .iter()
+                .find(|&&child| self.nodes[child].entry.name == name)?;
+        }
+        Some(id)
+    }
+
+    // Detach a subtree (e.g. after it was deleted on disk) and take its totals off the ancestors
+    pub fn remove(&mut self, id: NodeId) {
+        let Some(parent) = self.nodes[id].parent else { return };
+        self.nodes[parent].children.retain(|&child| child != id);
+        self.nodes[id].parent = None;
+
+        let (before, after) = self.nodes.split_at_mut(id);
+        let mut ancestor = Some(parent);
+        while let Some(current) = ancestor {
+            before[current].add_totals(&after[0], -1);
+            ancestor = before[current].parent;
+        }
+    }
+
+    pub fn folder_stats(&self, id: NodeId) -> FolderStats {
+        let node = &self.nodes[id];
+        FolderStats {
+            file_name: node.entry.name.clone(),
+            total_size: node.total_size,
+            allocated_size: node.allocated_size,
+            item_count: node.item_count + 1, // compute_folder_stats counts the folder itself
+            file_count: node.file_count,
+            subdir_count: node.subdir_count,
+            inode_count: node.inode_count,
+            shared_size: node.shared_size,
+            extra_links: node.extra_links,
+            excluded_size: node.excluded_size,
+            error_count: node.error_count,
+            last_modified: node.last_modified,
+        }
+    }
+
+    // Directories of the subtree holding the most entries, largest first
+    pub fn inode_hotspots(&self, id: NodeId, limit: usize) -> Vec<InodeHotspot> {
+        let mut hotspots: Vec<InodeHotspot> = self
+            .descendants(id)
+            .into_iter()
+            .filter(|&node| !self.nodes[node].children.is_empty())
+            .map(|node| InodeHotspot {
+                path: self.nodes[node].entry.path.clone(),
+                entry_count: self.nodes[node].children.len(),
+            })
+            .collect();
+
+        hotspots.sort_by(|a, b| b.entry_count.cmp(&a.entry_count).then_with(|| a.path.cmp(&b.path)));
+        hotspots.truncate(limit);
+        hotspots
+    }
+
+    // `id` and everything under it, parents before children
+    pub fn descendants(&self, id: NodeId) -> Vec<NodeId> {
+        let mut out = Vec::new();
+        let mut pending = vec![id];
+        while let Some(current) = pending.pop() {
+            out.push(current);
+            pending.extend(self.nodes[current].children.iter().rev());
+        }
+        out
+    }
+}
+
+impl DirNode {
+    // sign is 1 to add a child subtree, -1 to take one away
+    fn add_totals(&mut self, other: &DirNode, sign: i8) {
+        fn apply<T>(value: &mut T, delta: T, sign: i8)
+        where
+            T: std::ops::AddAssign + std::ops::SubAssign,
+        {
+            if sign >= 0 {
+                *value += delta;
+            } else {
+                *value -= delta;
+            }
+        }
+
+        apply(&mut self.total_size, other.total_size, sign);
+        apply(&mut self.allocated_size, other.allocated_size, sign);
+        apply(&mut self.item_count, other.item_count + 1, sign);
+        apply(&mut self.file_count, other.file_count, sign);
+        apply(&mut self.subdir_count, other.subdir_count, sign);
+        apply(&mut self.inode_count, other.inode_count, sign);
+        apply(&mut self.shared_size, other.shared_size, sign);
+        apply(&mut self.extra_links, other.extra_links, sign);
+        apply(&mut self.excluded_size, other.excluded_size, sign);
+        apply(&mut self.error_count, other.error_count, sign);
+
+        if sign >= 0 && other.last_modified > self.last_modified {
+            self.last_modified = other.last_modified;
+        }
+    }
+}
@@ -13,8 +13,42 @@ pub fn human_readable_size(size: u64) -> String {
     }
 }
 
+// Signed size change, e.g. "+1.20 MB" or "-512 B"
+pub fn human_readable_delta(delta: i64) -> String {
+    let sign = if delta < 0 { '-' } else { '+' };
+    format!("{}{}", sign, human_readable_size(delta.unsigned_abs()))
+}
+
 // Format a SystemTime into a human-readable string
 pub fn human_readable_time(time: std::time::SystemTime) -> String {
     let datetime: DateTime<Utc> = time.into();
     datetime.format("%d/%m/%Y %H:%M:%S").to_string()
 }
+
+// Format unix permission bits like `ls -l`, e.g. "rwxr-xr-x"
+pub fn human_readable_mode(mode: u32) -> String {
+    let flags = [
+        (0o400, 'r'), (0o200, 'w'), (0o100, 'x'),
+        (0o040, 'r'), (0o020, 'w'), (0o010, 'x'),
+        (0o004, 'r'), (0o002, 'w'), (0o001, 'x'),
+    ];
+    flags
+        .iter()
+        .map(|&(bit, c)| if mode & bit != 0 { c } else { '-' })
+        .collect()
+}
+
+// Rough age of something, e.g. "3 days ago" or "just now"
+pub fn human_readable_age(age: std::time::Duration) -> String {
+    let secs = age.as_secs();
+    let (count, unit) = if secs >= 86400 {
+        (secs / 86400, "day")
+    } else if secs >= 3600 {
+        (secs / 3600, "hour")
+    } else if secs >= 60 {
+        (secs / 60, "minute")
+    } else {
+        return "just now".to_string();
+    };
+    format!("{} {}{} ago", count, unit, if count == 1 { "" } else { "s" })
+}